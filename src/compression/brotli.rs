@@ -0,0 +1,783 @@
+use super::{
+    deflate::{BitWriter, BLOCK_SIZE},
+    lz77::{Matcher, MIN_MATCH},
+};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    io::{self, Read},
+    ops::Range,
+};
+
+// Insert and copy lengths of the insert-and-copy length codes (RFC 7932 section 5)
+const INSERT_BASE: [u32; 24] = [
+    0, 1, 2, 3, 4, 5, 6, 8, 10, 14, 18, 26, 34, 50, 66, 98, 130, 194, 322, 578, 1090, 2114, 6210,
+    22594,
+];
+const INSERT_EXTRA: [u32; 24] = [
+    0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 12, 14, 24,
+];
+const COPY_BASE: [u32; 24] = [
+    2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 18, 22, 30, 38, 54, 70, 102, 134, 198, 326, 582, 1094, 2118,
+];
+const COPY_EXTRA: [u32; 24] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 24,
+];
+
+// Alphabet sizes, and the bits a symbol takes in a simple prefix code
+const LITERALS: (usize, u32) = (256, 8);
+const COMMANDS: (usize, u32) = (704, 10);
+// 16 + NDIRECT + (48 << NPOSTFIX), both of which are 0 here
+const DISTANCES: (usize, u32) = (64, 6);
+
+// The order code length code lengths are written in (section 3.5)
+const CODE_LENGTH_ORDER: [usize; 18] =
+    [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+// The static code those lengths are written with, as (bits, count) by length
+const CODE_LENGTH_CODES: [(u32, u32); 6] = [(0, 2), (7, 4), (3, 3), (2, 2), (1, 2), (15, 4)];
+// Code lengths repeated by symbol 16 before any other was written
+const INITIAL_REPEATED_LENGTH: u8 = 8;
+
+// A brotli (RFC 7932) compressor. Matches are found the same way as for
+// DEFLATE, but each block becomes a meta-block with prefix codes built for its
+// own literals, lengths and distances. Input is fed in pieces and
+// back-references reach across them.
+pub struct BrotliCompressor {
+    matcher: Matcher,
+    bits: BitWriter,
+}
+
+impl BrotliCompressor {
+    pub fn new() -> BrotliCompressor {
+        let mut bits = BitWriter::default();
+        // WBITS = 16, a window of 64K - 16 bytes holds everything the matcher reaches
+        bits.write(0, 1);
+
+        BrotliCompressor {
+            matcher: Matcher::new(),
+            bits,
+        }
+    }
+
+    // Compresses one piece of input as non-final meta-blocks, returning the
+    // output bytes completed so far
+    pub fn compress(&mut self, input: &[u8]) -> Vec<u8> {
+        for block in input.chunks(BLOCK_SIZE) {
+            self.compress_block(block);
+        }
+        self.bits.take()
+    }
+
+    // Ends the stream with an empty final meta-block
+    pub fn finish(&mut self) -> Vec<u8> {
+        // ISLAST, ISLASTEMPTY
+        self.bits.write(1, 1);
+        self.bits.write(1, 1);
+        self.bits.flush();
+        self.bits.take()
+    }
+
+    fn compress_block(&mut self, block: &[u8]) {
+        let range = self.matcher.push(block);
+        let commands = self.commands(range);
+
+        let mut literal_counts = vec![0; LITERALS.0];
+        let mut command_counts = vec![0; COMMANDS.0];
+        let mut distance_counts = vec![0; DISTANCES.0];
+        for command in &commands {
+            for pos in command.literals.clone() {
+                literal_counts[self.matcher.byte(pos) as usize] += 1;
+            }
+            command_counts[command.code()] += 1;
+            if command.copy > 0 {
+                distance_counts[distance_code(command.distance).0] += 1;
+            }
+        }
+
+        // ISLAST = 0, MNIBBLES = 4 with MLEN - 1, ISUNCOMPRESSED = 0
+        self.bits.write(0, 1);
+        self.bits.write(0, 2);
+        self.bits.write(block.len() as u32 - 1, 16);
+        self.bits.write(0, 1);
+        // One block type each for literals, commands and distances, NPOSTFIX
+        // and NDIRECT of 0, the LSB6 context mode and a single literal and
+        // distance code, so no context maps
+        self.bits.write(0, 3);
+        self.bits.write(0, 2);
+        self.bits.write(0, 4);
+        self.bits.write(0, 2);
+        self.bits.write(0, 2);
+
+        let literal_code = write_prefix_code(&mut self.bits, &literal_counts, LITERALS.1);
+        let command_code = write_prefix_code(&mut self.bits, &command_counts, COMMANDS.1);
+        let distance_prefix = write_prefix_code(&mut self.bits, &distance_counts, DISTANCES.1);
+
+        for command in &commands {
+            let insert = command.literals.len() as u32;
+            let insert_code = length_code(&INSERT_BASE, insert);
+            // The copy of the last command is never read, any length will do
+            let copy = (command.copy as u32).max(COPY_BASE[0]);
+            let copy_code = length_code(&COPY_BASE, copy);
+
+            command_code.write(&mut self.bits, command.code());
+            self.bits
+                .write(insert - INSERT_BASE[insert_code], INSERT_EXTRA[insert_code]);
+            self.bits
+                .write(copy - COPY_BASE[copy_code], COPY_EXTRA[copy_code]);
+
+            for pos in command.literals.clone() {
+                literal_code.write(&mut self.bits, self.matcher.byte(pos) as usize);
+            }
+
+            if command.copy > 0 {
+                let (code, extra, count) = distance_code(command.distance);
+                distance_prefix.write(&mut self.bits, code);
+                self.bits.write(extra, count);
+            }
+        }
+
+        self.matcher.slide();
+    }
+
+    // Splits a block into literals and back-references
+    fn commands(&mut self, range: Range<usize>) -> Vec<Command> {
+        let end = range.end;
+        let mut commands = Vec::new();
+        let mut literals = range.start;
+        let mut pos = range.start;
+
+        while pos < end {
+            let (length, distance) = self.matcher.longest_match(pos, end);
+
+            if length >= MIN_MATCH {
+                commands.push(Command {
+                    literals: literals..pos,
+                    copy: length,
+                    distance,
+                });
+                for p in pos..pos + length {
+                    self.matcher.insert(p, end);
+                }
+                pos += length;
+                literals = pos;
+            } else {
+                self.matcher.insert(pos, end);
+                pos += 1;
+            }
+        }
+
+        // The meta-block ends after these literals, before a copy would be read
+        if literals < end {
+            commands.push(Command {
+                literals: literals..end,
+                copy: 0,
+                distance: 0,
+            });
+        }
+
+        commands
+    }
+}
+
+impl Default for BrotliCompressor {
+    fn default() -> BrotliCompressor {
+        BrotliCompressor::new()
+    }
+}
+
+// Literals to insert, followed by `copy` bytes from `distance` back
+struct Command {
+    literals: Range<usize>,
+    copy: usize,
+    distance: usize,
+}
+
+impl Command {
+    // The insert-and-copy length code, always one that has an explicit distance
+    fn code(&self) -> usize {
+        // The first cell of each insert code / 8 and copy code / 8 combination
+        const CELLS: [[usize; 3]; 3] = [[128, 192, 384], [256, 320, 512], [448, 576, 640]];
+
+        let insert = length_code(&INSERT_BASE, self.literals.len() as u32);
+        let copy = length_code(&COPY_BASE, (self.copy as u32).max(COPY_BASE[0]));
+        CELLS[insert / 8][copy / 8] + ((insert & 7) << 3) + (copy & 7)
+    }
+}
+
+fn length_code(bases: &[u32; 24], length: u32) -> usize {
+    bases.iter().rposition(|&base| base <= length).unwrap()
+}
+
+// The distance code, its extra bits and their count for NPOSTFIX = NDIRECT = 0.
+// Codes below 16 refer to earlier distances and aren't used.
+fn distance_code(distance: usize) -> (usize, u32, u32) {
+    let offset = distance + 3;
+    let count = usize::BITS - offset.leading_zeros() - 2;
+    let prefix = (offset >> count) & 1;
+    let code = 16 + 2 * (count as usize - 1) + prefix;
+    (code, (offset - ((2 + prefix) << count)) as u32, count)
+}
+
+// A prefix code as the code length and canonical code of each symbol
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u32>,
+}
+
+impl PrefixCode {
+    fn new(lengths: Vec<u8>) -> PrefixCode {
+        let mut counts = [0; 16];
+        for &length in &lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut next = [0; 16];
+        let mut code = 0;
+        for bits in 1..16 {
+            code = (code + counts[bits - 1]) << 1;
+            next[bits] = code;
+        }
+
+        let codes = lengths
+            .iter()
+            .map(|&length| {
+                let code = next[length as usize];
+                next[length as usize] += 1;
+                code
+            })
+            .collect();
+
+        PrefixCode { lengths, codes }
+    }
+
+    // Symbols of a code with a single symbol take no bits at all
+    fn write(&self, bits: &mut BitWriter, symbol: usize) {
+        let length = self.lengths[symbol] as u32;
+        if length > 0 {
+            bits.write_code(self.codes[symbol], length);
+        }
+    }
+}
+
+// Writes a prefix code fitting the symbol counts (section 3.4 and 3.5) and returns it
+fn write_prefix_code(bits: &mut BitWriter, counts: &[u32], alphabet_bits: u32) -> PrefixCode {
+    let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+
+    // A simple code with one symbol, HSKIP = 1 and NSYM - 1 = 0. Unused
+    // alphabets still need a code.
+    if used.len() < 2 {
+        bits.write(1, 2);
+        bits.write(0, 2);
+        bits.write(used.first().copied().unwrap_or(0) as u32, alphabet_bits);
+        return PrefixCode::new(vec![0; counts.len()]);
+    }
+
+    let lengths = code_lengths(counts, 15);
+    let tokens = length_tokens(&lengths);
+    let mut token_counts = [0; 18];
+    for &(symbol, _) in &tokens {
+        token_counts[symbol] += 1;
+    }
+
+    // A code length code with only one symbol takes no bits, the decoder
+    // recognizes it by reading all 18 lengths
+    let single = token_counts.iter().filter(|&&count| count > 0).count() == 1;
+    let token_lengths = match single {
+        true => token_counts.map(|count| (count > 0) as u8).to_vec(),
+        false => code_lengths(&token_counts, 5),
+    };
+    let last = match single {
+        true => CODE_LENGTH_ORDER.len() - 1,
+        false => CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&symbol| token_lengths[symbol] > 0)
+            .unwrap(),
+    };
+
+    // HSKIP = 0, then the lengths up to the last one in use
+    bits.write(0, 2);
+    for &symbol in &CODE_LENGTH_ORDER[..=last] {
+        let (code, count) = CODE_LENGTH_CODES[token_lengths[symbol] as usize];
+        bits.write(code, count);
+    }
+
+    let token_code = PrefixCode::new(match single {
+        true => vec![0; 18],
+        false => token_lengths,
+    });
+    for (symbol, extra) in tokens {
+        token_code.write(bits, symbol);
+        match symbol {
+            16 => bits.write(extra, 2),
+            17 => bits.write(extra, 3),
+            _ => {}
+        }
+    }
+
+    PrefixCode::new(lengths)
+}
+
+// Huffman code lengths for counts with at least two symbols in use, none
+// longer than `limit`. Rare symbols are counted as more common until the tree
+// is shallow enough.
+fn code_lengths(counts: &[u32], limit: u32) -> Vec<u8> {
+    let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+    let mut floor = 1;
+
+    loop {
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used
+            .iter()
+            .enumerate()
+            .map(|(node, &symbol)| Reverse((counts[symbol].max(floor) as u64, node)))
+            .collect();
+        let mut parent = vec![0; 2 * used.len() - 1];
+        let mut next = used.len();
+
+        while heap.len() > 1 {
+            let Reverse((left_weight, left)) = heap.pop().unwrap();
+            let Reverse((right_weight, right)) = heap.pop().unwrap();
+            parent[left] = next;
+            parent[right] = next;
+            heap.push(Reverse((left_weight + right_weight, next)));
+            next += 1;
+        }
+
+        // The root is the last node and parents come after their children
+        let mut depth = vec![0; next];
+        for node in (0..next - 1).rev() {
+            depth[node] = depth[parent[node]] + 1;
+        }
+
+        if depth[..used.len()].iter().all(|&d| d <= limit) {
+            let mut lengths = vec![0; counts.len()];
+            for (node, &symbol) in used.iter().enumerate() {
+                lengths[symbol] = depth[node] as u8;
+            }
+            return lengths;
+        }
+        floor *= 2;
+    }
+}
+
+// Code lengths as code length symbols with their extra bits. Runs use the
+// repeat symbols 16 (previous length) and 17 (zeros), trailing zeros are left
+// out since the decoder stops once the code is complete.
+fn length_tokens(lengths: &[u8]) -> Vec<(usize, u32)> {
+    let end = lengths.iter().rposition(|&l| l > 0).map_or(0, |i| i + 1);
+    let mut tokens = Vec::new();
+    let mut previous = INITIAL_REPEATED_LENGTH;
+    let mut i = 0;
+
+    while i < end {
+        let value = lengths[i];
+        let run = lengths[i..end].iter().take_while(|&&l| l == value).count();
+
+        if value == 0 {
+            repeat_tokens(&mut tokens, (0, 0), (17, 3), run);
+        } else {
+            let mut run = run;
+            if value != previous {
+                tokens.push((value as usize, 0));
+                run -= 1;
+                previous = value;
+            }
+            repeat_tokens(&mut tokens, (value as usize, 0), (16, 2), run);
+        }
+
+        i += run;
+    }
+
+    tokens
+}
+
+// Repeats a length `run` times. Consecutive repeat symbols combine, each one
+// multiplying the count before it, so the count is written in base 4 or 8
+// most significant digit first.
+fn repeat_tokens(
+    tokens: &mut Vec<(usize, u32)>,
+    single: (usize, u32),
+    (symbol, extra_bits): (usize, u32),
+    mut run: usize,
+) {
+    let digit = 1 << extra_bits;
+    // A run just past what one repeat symbol covers would need two of them
+    if run == digit + 3 {
+        tokens.push(single);
+        run -= 1;
+    }
+    if run < 3 {
+        tokens.extend(std::iter::repeat_n(single, run));
+        return;
+    }
+
+    let start = tokens.len();
+    run -= 3;
+    loop {
+        tokens.push((symbol, (run % digit) as u32));
+        run /= digit;
+        if run == 0 {
+            break;
+        }
+        run -= 1;
+    }
+    tokens[start..].reverse();
+}
+
+// Compresses everything read from `inner`, a block at a time
+pub struct BrotliReader<R> {
+    inner: R,
+    compressor: BrotliCompressor,
+    output: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R: Read> BrotliReader<R> {
+    pub fn new(inner: R) -> BrotliReader<R> {
+        BrotliReader {
+            inner,
+            compressor: BrotliCompressor::new(),
+            output: Vec::new(),
+            position: 0,
+            done: false,
+        }
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        let mut block = vec![0; BLOCK_SIZE];
+        let mut filled = 0;
+
+        while filled < block.len() {
+            match self.inner.read(&mut block[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.position = 0;
+        if filled > 0 {
+            self.output = self.compressor.compress(&block[..filled]);
+            return Ok(());
+        }
+
+        self.output = self.compressor.finish();
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BrotliReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.refill()?;
+        }
+
+        let n = buffer.len().min(self.output.len() - self.position);
+        buffer[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::Deflater;
+    use std::collections::HashMap;
+
+    // Reads bits least significant first
+    struct BitReader<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bit(&mut self) -> u32 {
+            let byte = self.data[self.position / 8];
+            let bit = (byte >> (self.position % 8)) & 1;
+            self.position += 1;
+            bit as u32
+        }
+
+        fn bits(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |value, i| value | (self.bit() << i))
+        }
+    }
+
+    // A prefix code read back from the stream
+    struct Decoder {
+        symbols: HashMap<(u32, u32), usize>,
+        single: Option<usize>,
+    }
+
+    impl Decoder {
+        fn new(lengths: &[u8]) -> Decoder {
+            let code = PrefixCode::new(lengths.to_vec());
+            let symbols = (0..lengths.len())
+                .filter(|&s| lengths[s] > 0)
+                .map(|s| ((lengths[s] as u32, code.codes[s]), s))
+                .collect();
+            Decoder {
+                symbols,
+                single: None,
+            }
+        }
+
+        fn single(symbol: usize) -> Decoder {
+            Decoder {
+                symbols: HashMap::new(),
+                single: Some(symbol),
+            }
+        }
+
+        // Codes start with their most significant bit
+        fn read(&self, reader: &mut BitReader) -> usize {
+            if let Some(symbol) = self.single {
+                return symbol;
+            }
+
+            let mut code = 0;
+            for length in 1..=15 {
+                code = (code << 1) | reader.bit();
+                if let Some(&symbol) = self.symbols.get(&(length, code)) {
+                    return symbol;
+                }
+            }
+            panic!("invalid prefix code");
+        }
+    }
+
+    fn read_prefix_code(reader: &mut BitReader, (size, alphabet_bits): (usize, u32)) -> Decoder {
+        if reader.bits(2) == 1 {
+            assert_eq!(reader.bits(2), 0, "expected a simple code with one symbol");
+            return Decoder::single(reader.bits(alphabet_bits) as usize);
+        }
+
+        let mut token_lengths = [0; 18];
+        let mut space = 32;
+        for &symbol in &CODE_LENGTH_ORDER {
+            let length = match (reader.bit(), reader.bit()) {
+                (0, 0) => 0,
+                (1, 0) => 4,
+                (0, 1) => 3,
+                _ => match reader.bit() {
+                    0 => 2,
+                    _ => match reader.bit() {
+                        0 => 1,
+                        _ => 5,
+                    },
+                },
+            };
+            token_lengths[symbol] = length;
+            if length > 0 {
+                space -= 32 >> length;
+                if space == 0 {
+                    break;
+                }
+            }
+        }
+        let used: Vec<usize> = (0..18).filter(|&s| token_lengths[s] > 0).collect();
+        let tokens = match used[..] {
+            [symbol] => Decoder::single(symbol),
+            _ => {
+                assert_eq!(space, 0, "incomplete code length code");
+                Decoder::new(&token_lengths)
+            }
+        };
+
+        let mut lengths = vec![0; size];
+        let mut space = 32768;
+        let mut previous = INITIAL_REPEATED_LENGTH;
+        let mut repeat = (0, 0);
+        let mut i = 0;
+        while space > 0 {
+            let symbol = tokens.read(reader);
+            if symbol < 16 {
+                lengths[i] = symbol as u8;
+                i += 1;
+                if symbol > 0 {
+                    previous = symbol as u8;
+                    space -= 32768 >> symbol;
+                }
+                repeat = (0, 0);
+                continue;
+            }
+
+            let (bits, value) = if symbol == 16 { (2, previous) } else { (3, 0) };
+            let extra = reader.bits(bits) as usize;
+            let old = if repeat.0 == symbol { repeat.1 } else { 0 };
+            let new = match old {
+                0 => 3 + extra,
+                _ => ((old - 2) << bits) + 3 + extra,
+            };
+            for _ in old..new {
+                lengths[i] = value;
+                i += 1;
+                if value > 0 {
+                    space -= 32768 >> value;
+                }
+            }
+            repeat = (symbol, new);
+        }
+        assert_eq!(space, 0, "incomplete prefix code");
+
+        Decoder::new(&lengths)
+    }
+
+    // Decodes the meta-blocks `BrotliCompressor` writes: one block type, one
+    // code per category and explicit distances only
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        // The first insert and copy code of each cell from 128 up
+        const CELLS: [(usize, usize); 9] = [
+            (0, 0),
+            (0, 8),
+            (8, 0),
+            (8, 8),
+            (0, 16),
+            (16, 0),
+            (8, 16),
+            (16, 8),
+            (16, 16),
+        ];
+
+        let mut reader = BitReader { data, position: 0 };
+        let mut out: Vec<u8> = Vec::new();
+        assert_eq!(reader.bit(), 0, "expected WBITS = 16");
+
+        while reader.bit() == 0 {
+            assert_eq!(reader.bits(2), 0, "expected MNIBBLES = 4");
+            let end = out.len() + reader.bits(16) as usize + 1;
+            assert_eq!(reader.bits(14), 0, "unexpected meta-block header");
+
+            let literals = read_prefix_code(&mut reader, LITERALS);
+            let commands = read_prefix_code(&mut reader, COMMANDS);
+            let distances = read_prefix_code(&mut reader, DISTANCES);
+
+            while out.len() < end {
+                let code = commands.read(&mut reader);
+                assert!(code >= 128, "implicit distances aren't written");
+                let (insert, copy) = CELLS[code / 64 - 2];
+                let (insert, copy) = (insert + ((code >> 3) & 7), copy + (code & 7));
+                let insert = INSERT_BASE[insert] + reader.bits(INSERT_EXTRA[insert]);
+                let copy = COPY_BASE[copy] + reader.bits(COPY_EXTRA[copy]);
+
+                for _ in 0..insert {
+                    out.push(literals.read(&mut reader) as u8);
+                }
+                if out.len() == end {
+                    break;
+                }
+
+                let code = distances.read(&mut reader) - 16;
+                let count = 1 + (code as u32 >> 1);
+                let offset = ((2 + (code & 1)) << count) - 4;
+                let distance = offset + reader.bits(count) as usize + 1;
+                assert!(distance <= out.len(), "distance reaches before the start");
+                for _ in 0..copy {
+                    out.push(out[out.len() - distance]);
+                }
+                assert!(out.len() <= end, "copy runs past the meta-block");
+            }
+        }
+
+        assert_eq!(reader.bit(), 1, "expected an empty last meta-block");
+        assert_eq!(reader.position.div_ceil(8), data.len());
+        assert_eq!(reader.bits((8 - reader.position % 8) as u32 % 8), 0);
+        out
+    }
+
+    fn compress(pieces: &[&[u8]]) -> Vec<u8> {
+        let mut compressor = BrotliCompressor::new();
+        let mut out = Vec::new();
+        for piece in pieces {
+            out.extend(compressor.compress(piece));
+        }
+        out.extend(compressor.finish());
+        out
+    }
+
+    // Deterministic bytes that don't compress
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545F491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = b"It was the best of times, it was the worst of times. ".repeat(2000);
+        let cases = [
+            Vec::new(),
+            b"a".to_vec(),
+            b"abababababababab".to_vec(),
+            b"abcabcabcabcabcabc".to_vec(),
+            vec![0; 300_000],
+            (0..=255).collect(),
+            noise(100_000),
+            noise(50_000).iter().map(|b| b % 5).collect(),
+            text,
+        ];
+
+        for input in cases {
+            assert_eq!(decompress(&compress(&[&input])), input);
+        }
+        assert_eq!(compress(&[]), [0x06]);
+    }
+
+    #[test]
+    fn matches_reach_across_pieces() {
+        let piece = noise(1000);
+        let compressed = compress(&[&piece, &piece, &piece]);
+
+        assert_eq!(decompress(&compressed), piece.repeat(3));
+        assert!(compressed.len() < 2 * piece.len());
+    }
+
+    #[test]
+    fn codes_fit_the_block() {
+        // Fixed Huffman codes spend 8 bits or more on every literal
+        let input: Vec<u8> = noise(100_000).iter().map(|b| b'a' + b % 16).collect();
+        let mut deflater = Deflater::new();
+        let mut deflated = deflater.compress(&input);
+        deflated.extend(deflater.finish());
+
+        let compressed = compress(&[&input]);
+        assert_eq!(decompress(&compressed), input);
+        assert!(compressed.len() < deflated.len() * 3 / 4);
+    }
+
+    #[test]
+    fn code_lengths_are_limited() {
+        // Fibonacci counts make the deepest Huffman trees
+        let mut counts = vec![1, 1];
+        while counts.len() < 30 {
+            counts.push(counts[counts.len() - 1] + counts[counts.len() - 2]);
+        }
+
+        let lengths = code_lengths(&counts, 15);
+        assert!(lengths.iter().all(|&length| (1..=15).contains(&length)));
+        let kraft: u32 = lengths.iter().map(|&length| 32768 >> length).sum();
+        assert_eq!(kraft, 32768);
+    }
+
+    #[test]
+    fn reader() {
+        let input = noise(150_000).repeat(2);
+        let mut out = Vec::new();
+        BrotliReader::new(&input[..]).read_to_end(&mut out).unwrap();
+
+        assert_eq!(decompress(&out), input);
+    }
+}
@@ -0,0 +1,142 @@
+// CRC-32 as used by gzip (IEEE polynomial, reflected)
+pub struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Crc32 {
+        let mut table = [0; 256];
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 {
+                    0xEDB88320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+            }
+            *entry = c;
+        }
+
+        Crc32 {
+            table,
+            crc: 0xFFFFFFFF,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.crc = self.table[((self.crc ^ byte as u32) & 0xFF) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        self.crc ^ 0xFFFFFFFF
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Crc32 {
+        Crc32::new()
+    }
+}
+
+// Adler-32 as used by zlib
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    const MOD: u32 = 65521;
+    // The most bytes that can be summed before `b` could overflow a u32
+    const NMAX: usize = 5552;
+
+    pub fn new() -> Adler32 {
+        Adler32 { a: 1, b: 0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for chunk in data.chunks(Self::NMAX) {
+            for &byte in chunk {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= Self::MOD;
+            self.b %= Self::MOD;
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+impl Default for Adler32 {
+    fn default() -> Adler32 {
+        Adler32::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_known_answers() {
+        let cases: [(&[u8], u32); 3] = [
+            (b"", 0),
+            (b"123456789", 0xCBF43926),
+            (b"The quick brown fox jumps over the lazy dog", 0x414FA339),
+        ];
+
+        for (input, crc) in cases {
+            let mut checksum = Crc32::new();
+            checksum.update(input);
+            assert_eq!(checksum.finish(), crc);
+        }
+    }
+
+    #[test]
+    fn adler32_known_answers() {
+        let cases: [(&[u8], u32); 2] = [(b"", 1), (b"Wikipedia", 0x11E60398)];
+
+        for (input, adler) in cases {
+            let mut checksum = Adler32::new();
+            checksum.update(input);
+            assert_eq!(checksum.finish(), adler);
+        }
+    }
+
+    // Long runs of 0xFF are the worst case for the deferred modulo
+    #[test]
+    fn adler32_matches_bytewise_modulo() {
+        let data = vec![0xFF; 3 * Adler32::NMAX + 17];
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in &data {
+            a = (a + byte as u32) % Adler32::MOD;
+            b = (b + a) % Adler32::MOD;
+        }
+
+        let mut checksum = Adler32::new();
+        checksum.update(&data);
+        assert_eq!(checksum.finish(), (b << 16) | a);
+    }
+
+    #[test]
+    fn incremental_matches_one_shot() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+        let (mut crc, mut adler) = (Crc32::new(), Adler32::new());
+        for piece in data.chunks(777) {
+            crc.update(piece);
+            adler.update(piece);
+        }
+
+        let (mut crc_once, mut adler_once) = (Crc32::new(), Adler32::new());
+        crc_once.update(&data);
+        adler_once.update(&data);
+        assert_eq!(crc.finish(), crc_once.finish());
+        assert_eq!(adler.finish(), adler_once.finish());
+    }
+}
@@ -0,0 +1,438 @@
+use super::{
+    checksum::{Adler32, Crc32},
+    lz77::{Matcher, MIN_MATCH},
+};
+use std::io::{self, Read};
+
+// Input is compressed in blocks of up to this size
+pub(super) const BLOCK_SIZE: usize = 64 * 1024;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// A DEFLATE (RFC 1951) compressor: LZ77 over a 32K sliding window, each block
+// coded with the fixed Huffman tables. Input is fed in pieces and
+// back-references reach across them.
+pub struct Deflater {
+    matcher: Matcher,
+    bits: BitWriter,
+}
+
+impl Deflater {
+    pub fn new() -> Deflater {
+        Deflater {
+            matcher: Matcher::new(),
+            bits: BitWriter::default(),
+        }
+    }
+
+    // Compresses one piece of input as a non-final block, returning the output
+    // bytes completed so far
+    pub fn compress(&mut self, input: &[u8]) -> Vec<u8> {
+        if input.is_empty() {
+            return self.bits.take();
+        }
+
+        let range = self.matcher.push(input);
+        let end = range.end;
+
+        // BFINAL = 0, BTYPE = 01 (fixed Huffman codes)
+        self.bits.write(0, 1);
+        self.bits.write(1, 2);
+
+        let mut pos = range.start;
+        while pos < end {
+            let (length, distance) = self.matcher.longest_match(pos, end);
+
+            if length >= MIN_MATCH {
+                self.write_match(length, distance);
+                for p in pos..pos + length {
+                    self.matcher.insert(p, end);
+                }
+                pos += length;
+            } else {
+                self.write_symbol(self.matcher.byte(pos) as u16);
+                self.matcher.insert(pos, end);
+                pos += 1;
+            }
+        }
+        self.write_symbol(256);
+        self.matcher.slide();
+
+        self.bits.take()
+    }
+
+    // Ends the stream with an empty final block
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.bits.write(1, 1);
+        self.bits.write(1, 2);
+        self.write_symbol(256);
+        self.bits.flush();
+        self.bits.take()
+    }
+
+    fn write_match(&mut self, length: usize, distance: usize) {
+        let code = LENGTH_BASE
+            .iter()
+            .rposition(|&base| base as usize <= length)
+            .unwrap();
+        self.write_symbol(257 + code as u16);
+        self.bits.write(
+            (length - LENGTH_BASE[code] as usize) as u32,
+            LENGTH_EXTRA[code] as u32,
+        );
+
+        let code = DISTANCE_BASE
+            .iter()
+            .rposition(|&base| base as usize <= distance)
+            .unwrap();
+        self.bits.write_code(code as u32, 5);
+        self.bits.write(
+            (distance - DISTANCE_BASE[code] as usize) as u32,
+            DISTANCE_EXTRA[code] as u32,
+        );
+    }
+
+    // A literal/length symbol with the fixed code table of RFC 1951 section 3.2.6
+    fn write_symbol(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.bits.write_code(0x30 + symbol, 8),
+            144..=255 => self.bits.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.bits.write_code(symbol - 256, 7),
+            _ => self.bits.write_code(0xC0 + symbol - 280, 8),
+        }
+    }
+}
+
+impl Default for Deflater {
+    fn default() -> Deflater {
+        Deflater::new()
+    }
+}
+
+// Packs values into bytes least significant bit first, as DEFLATE and brotli want
+#[derive(Default)]
+pub(super) struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    pub(super) fn write(&mut self, value: u32, count: u32) {
+        self.bits |= (value as u64) << self.count;
+        self.count += count;
+
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are packed starting with their most significant bit
+    pub(super) fn write_code(&mut self, code: u32, count: u32) {
+        self.write(code.reverse_bits() >> (32 - count), count);
+    }
+
+    // Pads the last byte with zeros
+    pub(super) fn flush(&mut self) {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+            self.bits = 0;
+            self.count = 0;
+        }
+    }
+
+    pub(super) fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.out)
+    }
+}
+
+// The container around the DEFLATE data
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // RFC 1952, the `gzip` content coding
+    Gzip,
+    // RFC 1950, which the `deflate` content coding means
+    Zlib,
+}
+
+// Compresses everything read from `inner`, a block at a time
+pub struct DeflateReader<R> {
+    inner: R,
+    format: Format,
+    deflater: Deflater,
+    output: Vec<u8>,
+    position: usize,
+    crc: Crc32,
+    adler: Adler32,
+    size: u32,
+    done: bool,
+}
+
+impl<R: Read> DeflateReader<R> {
+    pub fn new(inner: R, format: Format) -> DeflateReader<R> {
+        let header = match format {
+            // No file name or modification time, unknown OS
+            Format::Gzip => vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255],
+            // 32K window, no preset dictionary
+            Format::Zlib => vec![0x78, 0x01],
+        };
+
+        DeflateReader {
+            inner,
+            format,
+            deflater: Deflater::new(),
+            output: header,
+            position: 0,
+            crc: Crc32::new(),
+            adler: Adler32::new(),
+            size: 0,
+            done: false,
+        }
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        let mut block = vec![0; BLOCK_SIZE];
+        let mut filled = 0;
+
+        while filled < block.len() {
+            match self.inner.read(&mut block[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.position = 0;
+        if filled > 0 {
+            let block = &block[..filled];
+            self.crc.update(block);
+            self.adler.update(block);
+            self.size = self.size.wrapping_add(filled as u32);
+            self.output = self.deflater.compress(block);
+            return Ok(());
+        }
+
+        self.output = self.deflater.finish();
+        match self.format {
+            Format::Gzip => {
+                self.output
+                    .extend_from_slice(&self.crc.finish().to_le_bytes());
+                self.output.extend_from_slice(&self.size.to_le_bytes());
+            }
+            Format::Zlib => self
+                .output
+                .extend_from_slice(&self.adler.finish().to_be_bytes()),
+        }
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DeflateReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.refill()?;
+        }
+
+        let n = buffer.len().min(self.output.len() - self.position);
+        buffer[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads DEFLATE bits least significant first
+    struct BitReader<'a> {
+        data: &'a [u8],
+        position: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bit(&mut self) -> u32 {
+            let byte = self.data[self.position / 8];
+            let bit = (byte >> (self.position % 8)) & 1;
+            self.position += 1;
+            bit as u32
+        }
+
+        fn bits(&mut self, count: u8) -> u32 {
+            (0..count).fold(0, |value, i| value | (self.bit() << i))
+        }
+
+        // Huffman codes start with their most significant bit
+        fn code(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |code, _| (code << 1) | self.bit())
+        }
+    }
+
+    // Decodes a stream of fixed Huffman blocks, the only kind `Deflater` writes.
+    // Returns the data and the number of bytes the stream took up.
+    fn inflate(data: &[u8]) -> (Vec<u8>, usize) {
+        let mut reader = BitReader { data, position: 0 };
+        let mut out = Vec::new();
+
+        loop {
+            let last = reader.bits(1) == 1;
+            assert_eq!(reader.bits(2), 1, "expected a fixed Huffman block");
+
+            loop {
+                let mut code = reader.code(7);
+                let symbol = if code <= 0x17 {
+                    256 + code
+                } else {
+                    code = (code << 1) | reader.bit();
+                    match code {
+                        0x30..=0xBF => code - 0x30,
+                        0xC0..=0xC7 => 280 + code - 0xC0,
+                        _ => 144 + ((code << 1) | reader.bit()) - 0x190,
+                    }
+                };
+
+                match symbol {
+                    0..=255 => out.push(symbol as u8),
+                    256 => break,
+                    _ => {
+                        let i = (symbol - 257) as usize;
+                        let length =
+                            LENGTH_BASE[i] as usize + reader.bits(LENGTH_EXTRA[i]) as usize;
+                        let i = reader.code(5) as usize;
+                        let distance =
+                            DISTANCE_BASE[i] as usize + reader.bits(DISTANCE_EXTRA[i]) as usize;
+
+                        assert!(distance <= out.len(), "distance reaches before the start");
+                        for _ in 0..length {
+                            out.push(out[out.len() - distance]);
+                        }
+                    }
+                }
+            }
+
+            if last {
+                return (out, reader.position.div_ceil(8));
+            }
+        }
+    }
+
+    fn deflate(pieces: &[&[u8]]) -> Vec<u8> {
+        let mut deflater = Deflater::new();
+        let mut out = Vec::new();
+        for piece in pieces {
+            out.extend(deflater.compress(piece));
+        }
+        out.extend(deflater.finish());
+        out
+    }
+
+    // Deterministic bytes that don't compress
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545F491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = b"It was the best of times, it was the worst of times. ".repeat(2000);
+        let cases = [
+            Vec::new(),
+            b"a".to_vec(),
+            b"abcabcabcabcabcabc".to_vec(),
+            vec![0; 300_000],
+            (0..=255).collect(),
+            noise(100_000),
+            text,
+        ];
+
+        for input in cases {
+            let compressed = deflate(&[&input]);
+            let (output, used) = inflate(&compressed);
+            assert_eq!(output, input);
+            assert_eq!(used, compressed.len());
+        }
+    }
+
+    #[test]
+    fn matches_reach_across_pieces() {
+        let piece = noise(1000);
+        let compressed = deflate(&[&piece, &piece, &piece]);
+
+        assert_eq!(inflate(&compressed).0, piece.repeat(3));
+        assert!(compressed.len() < 2 * piece.len());
+    }
+
+    #[test]
+    fn compresses_repetitive_input() {
+        let input = b"<li class=\"item\">entry</li>\n".repeat(1000);
+        assert!(deflate(&[&input]).len() < input.len() / 10);
+    }
+
+    fn encode(input: &[u8], format: Format) -> Vec<u8> {
+        let mut out = Vec::new();
+        DeflateReader::new(input, format)
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn gzip_container() {
+        let input = noise(200_000);
+        let gzip = encode(&input, Format::Gzip);
+
+        assert_eq!(gzip[..4], [0x1f, 0x8b, 8, 0]);
+        let (output, used) = inflate(&gzip[10..]);
+        assert_eq!(output, input);
+
+        let trailer = &gzip[10 + used..];
+        let mut crc = Crc32::new();
+        crc.update(&input);
+        assert_eq!(trailer[..4], crc.finish().to_le_bytes());
+        assert_eq!(trailer[4..], (input.len() as u32).to_le_bytes());
+    }
+
+    #[test]
+    fn zlib_container() {
+        let input = b"hello hello hello zlib".repeat(100);
+        let zlib = encode(&input, Format::Zlib);
+
+        // CMF and FLG together are a multiple of 31
+        assert_eq!(u16::from_be_bytes([zlib[0], zlib[1]]) % 31, 0);
+        let (output, used) = inflate(&zlib[2..]);
+        assert_eq!(output, input);
+
+        let mut adler = Adler32::new();
+        adler.update(&input);
+        assert_eq!(zlib[2 + used..], adler.finish().to_be_bytes());
+    }
+}
@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+// How far back matches may reach, the maximum DEFLATE allows
+pub const WINDOW_SIZE: usize = 32 * 1024;
+pub const MIN_MATCH: usize = 3;
+pub const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+// Candidates tried per position, more compresses better but slower
+const MAX_CHAIN: usize = 64;
+const NONE: usize = usize::MAX;
+
+// Finds earlier occurrences of the input over a sliding window with hash
+// chains. Input is added in pieces and matches reach across them; positions
+// are offsets into the whole stream.
+pub struct Matcher {
+    // The last window of input plus the piece being compressed
+    history: Vec<u8>,
+    // Stream offset of `history[0]`
+    base: usize,
+    // Latest position for each hash, and the previous one with the same hash
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+impl Matcher {
+    pub fn new() -> Matcher {
+        Matcher {
+            history: Vec::new(),
+            base: 0,
+            head: vec![NONE; 1 << HASH_BITS],
+            prev: vec![NONE; WINDOW_SIZE],
+        }
+    }
+
+    // Appends a piece of input, returning the positions it takes up
+    pub fn push(&mut self, input: &[u8]) -> Range<usize> {
+        let start = self.base + self.history.len();
+        self.history.extend_from_slice(input);
+        start..self.base + self.history.len()
+    }
+
+    // Forgets input that no match can reach anymore
+    pub fn slide(&mut self) {
+        if self.history.len() > WINDOW_SIZE {
+            let excess = self.history.len() - WINDOW_SIZE;
+            self.history.drain(..excess);
+            self.base += excess;
+        }
+    }
+
+    pub fn byte(&self, pos: usize) -> u8 {
+        self.history[pos - self.base]
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let i = pos - self.base;
+        let key =
+            u32::from_le_bytes([self.history[i], self.history[i + 1], self.history[i + 2], 0]);
+        (key.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+    }
+
+    // Makes the bytes at `pos` findable by later positions
+    pub fn insert(&mut self, pos: usize, end: usize) {
+        if pos + MIN_MATCH > end {
+            return;
+        }
+
+        let hash = self.hash(pos);
+        self.prev[pos % WINDOW_SIZE] = self.head[hash];
+        self.head[hash] = pos;
+    }
+
+    // The longest earlier occurrence of the bytes at `pos`, as (length, distance)
+    pub fn longest_match(&self, pos: usize, end: usize) -> (usize, usize) {
+        if pos + MIN_MATCH > end {
+            return (0, 0);
+        }
+
+        let max = MAX_MATCH.min(end - pos);
+        let current = &self.history[pos - self.base..pos - self.base + max];
+        let mut best = (0, 0);
+        let mut candidate = self.head[self.hash(pos)];
+
+        for _ in 0..MAX_CHAIN {
+            if candidate == NONE
+                || candidate >= pos
+                || candidate < self.base
+                || pos - candidate > WINDOW_SIZE
+            {
+                break;
+            }
+
+            let earlier = &self.history[candidate - self.base..];
+            let length = current
+                .iter()
+                .zip(earlier)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.0 {
+                best = (length, pos - candidate);
+                if length == max {
+                    break;
+                }
+            }
+
+            let next = self.prev[candidate % WINDOW_SIZE];
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+
+        best
+    }
+}
+
+impl Default for Matcher {
+    fn default() -> Matcher {
+        Matcher::new()
+    }
+}
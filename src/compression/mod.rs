@@ -0,0 +1,271 @@
+pub use brotli::{BrotliCompressor, BrotliReader};
+pub use deflate::{DeflateReader, Deflater, Format};
+
+pub mod brotli;
+pub mod checksum;
+pub mod deflate;
+mod lz77;
+
+use crate::{
+    middleware::{Middleware, Next},
+    Body, ContentType, ETag, HttpMethod, HttpRequest, HttpResponse, StatusCode,
+};
+use std::io::Read;
+
+// A content coding the `Compression` middleware can apply. brotli, gzip and
+// deflate are built in, others can be plugged in with `Compression::encoder`.
+pub trait Encoder: Send + Sync {
+    // The Content-Encoding token, e.g. `br`
+    fn name(&self) -> &str;
+
+    // Wraps the body so that reading from it yields the encoded bytes
+    fn encode(&self, body: Box<dyn Read + Send>) -> Box<dyn Read + Send>;
+}
+
+pub struct Brotli;
+
+impl Encoder for Brotli {
+    fn name(&self) -> &str {
+        "br"
+    }
+
+    fn encode(&self, body: Box<dyn Read + Send>) -> Box<dyn Read + Send> {
+        Box::new(BrotliReader::new(body))
+    }
+}
+
+pub struct Gzip;
+
+impl Encoder for Gzip {
+    fn name(&self) -> &str {
+        "gzip"
+    }
+
+    fn encode(&self, body: Box<dyn Read + Send>) -> Box<dyn Read + Send> {
+        Box::new(DeflateReader::new(body, Format::Gzip))
+    }
+}
+
+pub struct Deflate;
+
+impl Encoder for Deflate {
+    fn name(&self) -> &str {
+        "deflate"
+    }
+
+    fn encode(&self, body: Box<dyn Read + Send>) -> Box<dyn Read + Send> {
+        Box::new(DeflateReader::new(body, Format::Zlib))
+    }
+}
+
+// Compresses responses in the best coding the client accepts (Accept-Encoding,
+// with q-values). Only text-like types above a size threshold are compressed;
+// images, video and archives are compressed already. Bodies up to the buffer
+// limit are compressed in memory to keep their Content-Length, larger ones and
+// those of unknown size are compressed while they are sent, chunked.
+pub struct Compression {
+    // In order of preference when the client likes several equally
+    encoders: Vec<Box<dyn Encoder>>,
+    threshold: u64,
+    buffer_limit: u64,
+}
+
+impl Compression {
+    pub fn new() -> Compression {
+        Compression {
+            encoders: vec![Box::new(Brotli), Box::new(Gzip), Box::new(Deflate)],
+            threshold: 1024,
+            buffer_limit: 1024 * 1024,
+        }
+    }
+
+    // Adds a coding, preferred over the ones registered before it
+    pub fn encoder<E: Encoder + 'static>(mut self, encoder: E) -> Compression {
+        self.encoders.insert(0, Box::new(encoder));
+        self
+    }
+
+    // Bodies smaller than this many bytes are sent as they are
+    pub fn threshold(mut self, bytes: u64) -> Compression {
+        self.threshold = bytes;
+        self
+    }
+
+    // Bodies larger than this are compressed while streaming
+    pub fn buffer_limit(mut self, bytes: u64) -> Compression {
+        self.buffer_limit = bytes;
+        self
+    }
+
+    // The coding to use for an Accept-Encoding header, None for identity
+    fn negotiate(&self, accept: &str) -> Option<&dyn Encoder> {
+        let preferences: Vec<(String, f32)> = accept
+            .split(',')
+            .filter_map(|entry| {
+                let mut params = entry.split(';');
+                let coding = params.next()?.trim().to_lowercase();
+                let quality = match params.find_map(|param| param.trim().strip_prefix("q=")) {
+                    Some(q) => q.trim().parse::<f32>().ok()?.clamp(0.0, 1.0),
+                    None => 1.0,
+                };
+                (!coding.is_empty()).then_some((coding, quality))
+            })
+            .collect();
+
+        let quality = |name: &str| {
+            let find = |wanted: &str| {
+                preferences
+                    .iter()
+                    .find(|(coding, _)| coding == wanted)
+                    .map(|(_, q)| *q)
+            };
+            // x-gzip is an old alias of gzip
+            find(name)
+                .or_else(|| (name == "gzip").then(|| find("x-gzip")).flatten())
+                .or_else(|| find("*"))
+                .unwrap_or(0.0)
+        };
+
+        let mut best: Option<(&dyn Encoder, f32)> = None;
+        for encoder in &self.encoders {
+            let q = quality(encoder.name());
+            if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+                best = Some((encoder.as_ref(), q));
+            }
+        }
+
+        best.map(|(encoder, _)| encoder)
+    }
+
+    // Whether compressing the response could change it at all
+    fn eligible(&self, response: &HttpResponse) -> bool {
+        let compressible = response
+            .headers
+            .content_type()
+            .is_some_and(|content_type| is_compressible(&content_type));
+
+        compressible && response.status.allows_body() && self.transformable(response)
+    }
+
+    // A 304 has no body to go by. Its Content-Type and Content-Length, where
+    // given, describe the cached 200.
+    fn revalidates(&self, response: &HttpResponse) -> bool {
+        let compressible = response
+            .headers
+            .content_type()
+            .is_none_or(|content_type| is_compressible(&content_type));
+
+        compressible && response.status == StatusCode::NOT_MODIFIED && self.transformable(response)
+    }
+
+    fn transformable(&self, response: &HttpResponse) -> bool {
+        !response.headers.contains("Content-Encoding")
+            && !response.headers.has_token("Cache-Control", "no-transform")
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Compression {
+        Compression::new()
+    }
+}
+
+impl Middleware for Compression {
+    fn handle(&self, request: HttpRequest, next: Next<'_>) -> HttpResponse {
+        let encoder = request
+            .header("Accept-Encoding")
+            .and_then(|accept| self.negotiate(accept));
+        let head = request.method == HttpMethod::HEAD;
+        let mut response = next.run(request);
+
+        // A 304 stands in for the 200 the client has cached, which was compressed
+        // if this request negotiates a coding. Its headers have to match.
+        if self.revalidates(&response) {
+            vary(&mut response);
+            let length = response.headers.content_length();
+            if encoder.is_some() && length.is_none_or(|length| length >= self.threshold) {
+                weaken_etag(&mut response);
+            }
+            return response;
+        }
+        if !self.eligible(&response) {
+            return response;
+        }
+
+        vary(&mut response);
+
+        let Some(encoder) = encoder else {
+            return response;
+        };
+        // Ranges refer to the identity body, so partial responses go out as they are
+        if response.status.as_u16() == 206 {
+            return response;
+        }
+
+        // HEAD answers carry the size of the uncompressed body, if any
+        let length = match response.headers.content_length() {
+            Some(length) if head => Some(length),
+            _ => response.body.len(),
+        };
+        if length.is_some_and(|length| length < self.threshold) {
+            return response;
+        }
+
+        response.headers.insert("Content-Encoding", encoder.name());
+        response.headers.remove("Content-Length");
+        response.headers.remove("Accept-Ranges");
+        weaken_etag(&mut response);
+
+        if head {
+            response.headers.insert("Transfer-Encoding", "chunked");
+            return response;
+        }
+
+        let body = std::mem::take(&mut response.body);
+        let mut encoded = encoder.encode(body.into_reader());
+        response.body = match length {
+            Some(length) if length <= self.buffer_limit => {
+                let mut bytes = Vec::new();
+                if let Err(e) = encoded.read_to_end(&mut bytes) {
+                    println!("Failed to compress response: {}", e);
+                    return HttpResponse::internal_server_error();
+                }
+                Body::from(bytes)
+            }
+            _ => Body::from_reader(encoded, None),
+        };
+
+        response
+    }
+}
+
+// Caches must keep the variants apart even when this one isn't compressed
+fn vary(response: &mut HttpResponse) {
+    if !response.headers.has_token("Vary", "accept-encoding")
+        && !response.headers.has_token("Vary", "*")
+    {
+        response.headers.append("Vary", "Accept-Encoding");
+    }
+}
+
+// The compressed bytes differ, so a strong tag of the original no longer fits
+fn weaken_etag(response: &mut HttpResponse) {
+    if let Some(etag) = response.header("ETag").and_then(ETag::parse) {
+        let etag = ETag::weak(&etag.tag);
+        response.headers.insert("ETag", &etag.to_string());
+    }
+}
+
+// Text and other formats that aren't compressed already
+fn is_compressible(content_type: &ContentType) -> bool {
+    content_type.is_text()
+        || matches!(
+            content_type.mime(),
+            "application/wasm"
+                | "application/vnd.ms-fontobject"
+                | "font/ttf"
+                | "font/otf"
+                | "image/bmp"
+                | "image/x-icon"
+        )
+}
@@ -0,0 +1,25 @@
+pub use hmac::hmac_sha256;
+pub use sha256::{sha256, Sha256};
+
+pub mod base64;
+pub mod hmac;
+pub mod sha256;
+
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
+// Bytes from the operating system's random source. There is no fallback: keys,
+// nonces and session ids derived from anything weaker could be guessed, so
+// callers get the error instead.
+pub fn random_bytes(len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; len];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+// Compares MACs without leaking through timing how much of them matched
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
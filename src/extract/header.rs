@@ -0,0 +1,84 @@
+use super::{rejection, FromRequest};
+use crate::{HttpError, HttpRequest, StatusCode};
+
+// A header with a known name that parses into a typed value
+pub trait TypedHeader: Sized {
+    const NAME: &'static str;
+
+    fn parse(value: &str) -> Result<Self, String>;
+}
+
+// A required typed header, missing or unparsable headers get a 400.
+// Wrap it in an Option for optional headers.
+pub struct Header<T>(pub T);
+
+impl<T: TypedHeader> FromRequest for Header<T> {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        let value = request.header(T::NAME).ok_or_else(|| {
+            rejection(
+                StatusCode::BAD_REQUEST,
+                &format!("Missing {} header", T::NAME),
+            )
+        })?;
+
+        T::parse(value).map(Header).map_err(|e| {
+            rejection(
+                StatusCode::BAD_REQUEST,
+                &format!("Invalid {} header: {}", T::NAME, e),
+            )
+        })
+    }
+}
+
+pub struct Host(pub String);
+
+impl TypedHeader for Host {
+    const NAME: &'static str = "Host";
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "" => Err("empty host".to_string()),
+            host => Ok(Host(host.to_string())),
+        }
+    }
+}
+
+pub struct UserAgent(pub String);
+
+impl TypedHeader for UserAgent {
+    const NAME: &'static str = "User-Agent";
+
+    fn parse(value: &str) -> Result<Self, String> {
+        Ok(UserAgent(value.to_string()))
+    }
+}
+
+// The credentials of an `Authorization: <scheme> <credentials>` header
+pub struct Authorization {
+    pub scheme: String,
+    pub credentials: String,
+}
+
+impl Authorization {
+    // The token of a `Bearer` authorization
+    pub fn bearer(&self) -> Option<&str> {
+        match self.scheme.eq_ignore_ascii_case("Bearer") {
+            true => Some(&self.credentials),
+            false => None,
+        }
+    }
+}
+
+impl TypedHeader for Authorization {
+    const NAME: &'static str = "Authorization";
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().split_once(' ') {
+            Some((scheme, credentials)) if !credentials.trim().is_empty() => Ok(Authorization {
+                scheme: scheme.to_string(),
+                credentials: credentials.trim().to_string(),
+            }),
+            _ => Err("expected `<scheme> <credentials>`".to_string()),
+        }
+    }
+}
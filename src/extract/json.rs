@@ -0,0 +1,111 @@
+use super::{media_type, rejection, FromRequest};
+use crate::{
+    json::{self, FromJson, ToJson},
+    Body, ContentType, HttpError, HttpRequest, HttpResponse, IntoResponse, StatusCode,
+};
+
+// How JSON bodies are read and written, register a different one with `Router::state`.
+// `Json` responses get the configured formatting, handlers can take it as an
+// argument for other responses. The parser reads the whole body before any
+// extractor runs, so a body has to fit `ParserLimits::max_body_size` to be
+// checked against `max_body_size` here at all.
+#[derive(Clone, Copy)]
+pub struct JsonConfig {
+    // Indentation for responses, compact when None
+    pub pretty: Option<usize>,
+    // Largest request body the extractor accepts
+    pub max_body_size: usize,
+}
+
+impl Default for JsonConfig {
+    fn default() -> JsonConfig {
+        JsonConfig {
+            pretty: None,
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+impl JsonConfig {
+    // The config registered with the router, or the default
+    pub(crate) fn of(request: &HttpRequest) -> JsonConfig {
+        request
+            .extensions
+            .get::<JsonConfig>()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn to_string<T: ToJson + ?Sized>(&self, value: &T) -> String {
+        let value = value.to_json();
+        match self.pretty {
+            Some(indent) => value.to_pretty(indent),
+            None => value.to_compact(),
+        }
+    }
+
+    // An application/json response formatted with this config
+    pub fn response<T: ToJson + ?Sized>(&self, value: &T) -> HttpResponse {
+        let mut response = HttpResponse::new(StatusCode::OK);
+        response.headers.set_content_type(ContentType::JSON);
+        response.body = Body::from(self.to_string(value));
+        response
+    }
+}
+
+impl FromRequest for JsonConfig {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        Ok(JsonConfig::of(request))
+    }
+}
+
+// A JSON body, or a response formatted with the router's `JsonConfig`. Other
+// content types get a 415, bodies over `JsonConfig::max_body_size` a 413,
+// malformed JSON a 400 and documents that don't fit `T` a 422.
+pub struct Json<T>(pub T);
+
+impl<T: FromJson> FromRequest for Json<T> {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        // Structured syntax suffixes like application/problem+json are JSON too
+        let is_json = media_type(request)
+            .is_some_and(|media| media == "application/json" || media.ends_with("+json"));
+        if !is_json {
+            return Err(rejection(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected an application/json body",
+            ));
+        }
+
+        let config = JsonConfig::from_request(request)?;
+        if request.body.len() > config.max_body_size {
+            return Err(rejection(
+                StatusCode::CONTENT_TOO_LARGE,
+                "JSON body too large",
+            ));
+        }
+
+        let body = request
+            .body_text()
+            .ok_or_else(|| rejection(StatusCode::BAD_REQUEST, "JSON body is not valid UTF-8"))?;
+        let value = json::parse(body)
+            .map_err(|e| rejection(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {}", e)))?;
+
+        T::from_json(&value).map(Json).map_err(|e| {
+            rejection(
+                StatusCode::UNPROCESSABLE_CONTENT,
+                &format!("Invalid JSON body: {}", e),
+            )
+        })
+    }
+}
+
+// Compact unless converted with the router's config
+impl<T: ToJson> IntoResponse for Json<T> {
+    fn into_response(self) -> HttpResponse {
+        self.into_response_with(&JsonConfig::default())
+    }
+
+    fn into_response_with(self, config: &JsonConfig) -> HttpResponse {
+        config.response(&self.0)
+    }
+}
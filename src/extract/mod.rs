@@ -0,0 +1,104 @@
+pub use header::{Authorization, Header, Host, TypedHeader, UserAgent};
+pub use json::{Json, JsonConfig};
+pub use multipart::{FormLimits, Multipart, UploadedFile};
+pub use params::{Form, FromParams, Params, Path, Query};
+pub use state::State;
+
+pub mod header;
+pub mod json;
+pub mod multipart;
+pub mod params;
+pub mod state;
+
+use crate::{Cookies, HttpError, HttpRequest, HttpResponse, IntoResponse, StatusCode};
+
+// A typed value taken from the request, used as a handler argument with `extract`.
+// Failing to extract answers the request with the returned error instead of
+// calling the handler.
+pub trait FromRequest: Sized {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError>;
+}
+
+// The whole request, for handlers that need more than the extractors give them
+impl FromRequest for HttpRequest {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        Ok(request.clone())
+    }
+}
+
+// Makes an extractor optional, failures become None
+impl<T: FromRequest> FromRequest for Option<T> {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        Ok(T::from_request(request).ok())
+    }
+}
+
+impl FromRequest for Cookies {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        Ok(request.cookies())
+    }
+}
+
+// A function whose arguments can all be extracted from a request
+pub trait Extract<Args>: Send + Sync + 'static {
+    fn call(&self, request: HttpRequest) -> HttpResponse;
+}
+
+impl<F, R> Extract<()> for F
+where
+    F: Fn() -> R + Send + Sync + 'static,
+    R: IntoResponse,
+{
+    fn call(&self, request: HttpRequest) -> HttpResponse {
+        self().into_response_with(&JsonConfig::of(&request))
+    }
+}
+
+macro_rules! extract_fn {
+    ($($arg:ident),+) => {
+        impl<F, R, $($arg),+> Extract<($($arg,)+)> for F
+        where
+            F: Fn($($arg),+) -> R + Send + Sync + 'static,
+            R: IntoResponse,
+            $($arg: FromRequest),+
+        {
+            #[allow(non_snake_case)]
+            fn call(&self, request: HttpRequest) -> HttpResponse {
+                $(let $arg = match $arg::from_request(&request) {
+                    Ok(value) => value,
+                    Err(e) => return e.into_response(),
+                };)+
+
+                self($($arg),+).into_response_with(&JsonConfig::of(&request))
+            }
+        }
+    };
+}
+
+extract_fn!(A);
+extract_fn!(A, B);
+extract_fn!(A, B, C);
+extract_fn!(A, B, C, D);
+extract_fn!(A, B, C, D, E);
+extract_fn!(A, B, C, D, E, G);
+
+// Turns a function taking extractors into a route handler, e.g.
+// `router.get("/users/:id", extract(|Path(user): Path<UserId>, Query(page): Query<Page>| ...))`
+pub fn extract<F, Args>(f: F) -> impl Fn(HttpRequest) -> HttpResponse + Send + Sync + 'static
+where
+    F: Extract<Args>,
+    Args: 'static,
+{
+    move |request| f.call(request)
+}
+
+// The content type of the request without parameters, lowercased
+fn media_type(request: &HttpRequest) -> Option<String> {
+    request
+        .header("Content-Type")
+        .map(|value| value.split(';').next().unwrap_or("").trim().to_lowercase())
+}
+
+fn rejection(status: StatusCode, message: &str) -> HttpError {
+    HttpError::new(status).with_message(message)
+}
@@ -0,0 +1,679 @@
+use super::{media_type, rejection, FromRequest};
+use crate::{
+    http::{spooled::temp_file, urlencoded},
+    HttpError, HttpRequest, StatusCode,
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+// Size limits for form bodies, register different ones with `Router::state`.
+// While they are received, multipart bodies are also held to
+// `ParserLimits::max_spooled_size` and urlencoded ones to `max_body_size`, so a
+// `max_total_size` above those needs the parser limits raised too.
+#[derive(Clone, Copy)]
+pub struct FormLimits {
+    // Largest text field, in memory
+    pub max_field_size: usize,
+    // Largest uploaded file, on disk
+    pub max_file_size: u64,
+    // Everything together
+    pub max_total_size: u64,
+    pub max_parts: usize,
+}
+
+impl Default for FormLimits {
+    fn default() -> FormLimits {
+        FormLimits {
+            max_field_size: 64 * 1024,
+            max_file_size: 8 * 1024 * 1024,
+            max_total_size: 10 * 1024 * 1024,
+            max_parts: 100,
+        }
+    }
+}
+
+impl FormLimits {
+    pub(crate) fn of(request: &HttpRequest) -> FormLimits {
+        request
+            .extensions
+            .get::<FormLimits>()
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+// A file part of a multipart form, written to a temporary file that is
+// deleted when this is dropped unless it was persisted
+pub struct UploadedFile {
+    // Name of the form field
+    pub name: String,
+    // The client's file name with any directories stripped, never trust it as a path
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub size: u64,
+    path: PathBuf,
+}
+
+impl UploadedFile {
+    // Where the upload sits until it is dropped
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> io::Result<File> {
+        File::open(&self.path)
+    }
+
+    // Moves the upload to `destination` so it outlives the request. A renamed
+    // file keeps its owner-only permissions.
+    pub fn persist(mut self, destination: &Path) -> io::Result<()> {
+        // Renaming fails across file systems, copy then
+        if fs::rename(&self.path, destination).is_err() {
+            fs::copy(&self.path, destination)?;
+            let _ = fs::remove_file(&self.path);
+        }
+
+        self.path = PathBuf::new();
+        Ok(())
+    }
+}
+
+impl Drop for UploadedFile {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+// A `multipart/form-data` body: text fields in order and uploaded files.
+// Bodies of other types get a 415, malformed ones a 400 and bodies over the
+// `FormLimits` a 413.
+pub struct Multipart {
+    pub fields: Vec<(String, String)>,
+    pub files: Vec<UploadedFile>,
+}
+
+impl Multipart {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn file(&self, name: &str) -> Option<&UploadedFile> {
+        self.files.iter().find(|file| file.name == name)
+    }
+
+    // Takes ownership of an upload, e.g. to persist it
+    pub fn take_file(&mut self, name: &str) -> Option<UploadedFile> {
+        let index = self.files.iter().position(|file| file.name == name)?;
+        Some(self.files.remove(index))
+    }
+
+    // Parses a multipart body from any reader, files go to disk as they are read
+    pub fn parse<R: Read>(
+        reader: R,
+        boundary: &str,
+        limits: FormLimits,
+    ) -> Result<Multipart, HttpError> {
+        MultipartParser::new(reader, boundary, limits).parse()
+    }
+}
+
+impl FromRequest for Multipart {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        if media_type(request).as_deref() != Some("multipart/form-data") {
+            return Err(rejection(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected a multipart/form-data body",
+            ));
+        }
+
+        let boundary = request
+            .header("Content-Type")
+            .and_then(|value| parameter(value, "boundary"))
+            .filter(|boundary| !boundary.is_empty() && boundary.len() <= 70)
+            .ok_or_else(|| rejection(StatusCode::BAD_REQUEST, "Missing multipart boundary"))?;
+
+        // The parser spools these bodies to disk, only requests built in memory
+        // carry them in `body`
+        let limits = FormLimits::of(request);
+        match &request.spooled {
+            Some(spooled) => {
+                let file = spooled.open().map_err(HttpError::internal)?;
+                Multipart::parse(file, &boundary, limits)
+            }
+            None => Multipart::parse(&request.body[..], &boundary, limits),
+        }
+    }
+}
+
+const MAX_PART_HEADERS: usize = 16 * 1024;
+const READ_SIZE: usize = 16 * 1024;
+
+struct MultipartParser<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    eof: bool,
+    // `\r\n--boundary`, the preamble delimiter lacks the leading CRLF
+    delimiter: Vec<u8>,
+    limits: FormLimits,
+    total: u64,
+}
+
+// Where the bytes of a part go while it is read
+enum Sink {
+    Field(Vec<u8>),
+    File(BufWriter<File>, PathBuf),
+}
+
+impl<R: Read> MultipartParser<R> {
+    fn new(reader: R, boundary: &str, limits: FormLimits) -> MultipartParser<R> {
+        MultipartParser {
+            reader,
+            buffer: Vec::new(),
+            eof: false,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            limits,
+            total: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<Multipart, HttpError> {
+        let mut form = Multipart {
+            fields: Vec::new(),
+            files: Vec::new(),
+        };
+
+        // Skip the preamble up to the first boundary
+        self.buffer.extend_from_slice(b"\r\n");
+        self.skip_to_delimiter()?;
+
+        let mut parts = 0;
+        while !self.after_delimiter()? {
+            parts += 1;
+            if parts > self.limits.max_parts {
+                return Err(too_large("Too many form parts"));
+            }
+
+            let headers = self.read_part_headers()?;
+            let disposition = header_value(&headers, "Content-Disposition")
+                .filter(|value| value.to_lowercase().starts_with("form-data"))
+                .ok_or_else(|| malformed("Part without form-data disposition"))?;
+            let name = parameter(&disposition, "name")
+                .ok_or_else(|| malformed("Part without a field name"))?;
+            let filename = parameter(&disposition, "filename*")
+                .map(|encoded| decode_extended(&encoded))
+                .or_else(|| parameter(&disposition, "filename"));
+            let content_type = header_value(&headers, "Content-Type");
+
+            // Parts with a filename are files, even when it's empty (no file chosen)
+            match filename {
+                Some(filename) => {
+                    let (path, size) = self.read_file()?;
+                    let filename = basename(&filename);
+                    form.files.push(UploadedFile {
+                        name,
+                        filename: (!filename.is_empty()).then_some(filename),
+                        content_type,
+                        size,
+                        path,
+                    });
+                }
+                None => {
+                    let value = self.read_field()?;
+                    let value = String::from_utf8(value)
+                        .map_err(|_| malformed("Form field is not valid UTF-8"))?;
+                    form.fields.push((name, value));
+                }
+            }
+        }
+
+        Ok(form)
+    }
+
+    // Reads part headers up to the blank line ending them
+    fn read_part_headers(&mut self) -> Result<Vec<(String, String)>, HttpError> {
+        loop {
+            if let Some(end) = find(&self.buffer, b"\r\n\r\n") {
+                let head: Vec<u8> = self.buffer.drain(..end + 4).collect();
+                let head = std::str::from_utf8(&head[..end])
+                    .map_err(|_| malformed("Part headers are not valid UTF-8"))?;
+
+                return head
+                    .split("\r\n")
+                    .filter(|line| !line.is_empty())
+                    .map(|line| {
+                        line.split_once(':')
+                            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                            .ok_or_else(|| malformed("Invalid part header"))
+                    })
+                    .collect();
+            }
+
+            // A part without headers starts with the blank line right away
+            if self.buffer.starts_with(b"\r\n") {
+                self.buffer.drain(..2);
+                return Ok(Vec::new());
+            }
+            if self.buffer.len() > MAX_PART_HEADERS {
+                return Err(too_large("Part headers too large"));
+            }
+            if !self.fill()? {
+                return Err(malformed("Unexpected end of multipart body"));
+            }
+        }
+    }
+
+    fn read_field(&mut self) -> Result<Vec<u8>, HttpError> {
+        match self.read_part(Sink::Field(Vec::new()))? {
+            Sink::Field(value) => Ok(value),
+            Sink::File(..) => unreachable!(),
+        }
+    }
+
+    fn read_file(&mut self) -> Result<(PathBuf, u64), HttpError> {
+        let (file, path) = temp_file("upload").map_err(HttpError::internal)?;
+
+        // Remove the temp file if anything goes wrong before it is handed out
+        match self.read_part(Sink::File(BufWriter::new(file), path.clone())) {
+            Ok(Sink::File(mut writer, path)) => {
+                writer.flush().map_err(HttpError::internal)?;
+                let size = writer
+                    .get_ref()
+                    .metadata()
+                    .map_err(HttpError::internal)?
+                    .len();
+                Ok((path, size))
+            }
+            Ok(Sink::Field(_)) => unreachable!(),
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                Err(e)
+            }
+        }
+    }
+
+    // Moves bytes into `sink` until the next delimiter, holding back just enough
+    // to spot a delimiter split across reads
+    fn read_part(&mut self, mut sink: Sink) -> Result<Sink, HttpError> {
+        let mut size: u64 = 0;
+
+        loop {
+            let (end, found) = match find(&self.buffer, &self.delimiter) {
+                Some(index) => (index, true),
+                None => (
+                    self.buffer.len().saturating_sub(self.delimiter.len() - 1),
+                    false,
+                ),
+            };
+
+            size += end as u64;
+            self.check_size(&sink, size)?;
+            match &mut sink {
+                Sink::Field(value) => value.extend_from_slice(&self.buffer[..end]),
+                Sink::File(writer, _) => writer
+                    .write_all(&self.buffer[..end])
+                    .map_err(HttpError::internal)?,
+            }
+
+            if found {
+                self.buffer.drain(..end + self.delimiter.len());
+                return Ok(sink);
+            }
+
+            self.buffer.drain(..end);
+            if !self.fill()? {
+                return Err(malformed("Unexpected end of multipart body"));
+            }
+        }
+    }
+
+    fn check_size(&self, sink: &Sink, size: u64) -> Result<(), HttpError> {
+        match sink {
+            Sink::Field(_) if size > self.limits.max_field_size as u64 => {
+                Err(too_large("Form field too large"))
+            }
+            Sink::File(..) if size > self.limits.max_file_size => {
+                Err(too_large("Uploaded file too large"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn skip_to_delimiter(&mut self) -> Result<(), HttpError> {
+        loop {
+            if let Some(index) = find(&self.buffer, &self.delimiter) {
+                self.buffer.drain(..index + self.delimiter.len());
+                return Ok(());
+            }
+
+            let keep = self.delimiter.len() - 1;
+            let drop = self.buffer.len().saturating_sub(keep);
+            self.buffer.drain(..drop);
+            if !self.fill()? {
+                return Err(malformed("Multipart boundary not found"));
+            }
+        }
+    }
+
+    // After a delimiter comes `--` for the last one, or CRLF before the next part.
+    // Returns whether the body is done.
+    fn after_delimiter(&mut self) -> Result<bool, HttpError> {
+        loop {
+            if self.buffer.starts_with(b"--") {
+                return Ok(true);
+            }
+
+            // Transport padding (whitespace) is allowed before the CRLF
+            let padding = self
+                .buffer
+                .iter()
+                .take_while(|b| **b == b' ' || **b == b'\t')
+                .count();
+            match &self.buffer[padding..] {
+                [b'\r', b'\n', ..] => {
+                    self.buffer.drain(..padding + 2);
+                    return Ok(false);
+                }
+                // The rest of the line may still be on its way
+                [] | [b'\r'] | [b'-'] if padding <= MAX_PART_HEADERS => {}
+                _ => return Err(malformed("Invalid multipart delimiter")),
+            }
+
+            if !self.fill()? {
+                return Err(malformed("Unexpected end of multipart body"));
+            }
+        }
+    }
+
+    // Reads more of the body, false at its end
+    fn fill(&mut self) -> Result<bool, HttpError> {
+        if self.eof {
+            return Ok(false);
+        }
+
+        let mut chunk = [0; READ_SIZE];
+        let read = self.reader.read(&mut chunk).map_err(HttpError::internal)?;
+        if read == 0 {
+            self.eof = true;
+            return Ok(false);
+        }
+
+        self.total += read as u64;
+        if self.total > self.limits.max_total_size {
+            return Err(too_large("Form body too large"));
+        }
+
+        self.buffer.extend_from_slice(&chunk[..read]);
+        Ok(true)
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn header_value(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.to_string())
+}
+
+// A `name=value` or `name="quoted value"` parameter of a header such as
+// `form-data; name="file"; filename="a;b.txt"`
+pub(crate) fn parameter(header: &str, name: &str) -> Option<String> {
+    let mut rest = header;
+
+    while let Some(index) = rest.find(';') {
+        rest = rest[index + 1..].trim_start();
+        let (key, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+
+        let (value, remainder) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+
+        if key.trim().eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+        rest = remainder;
+    }
+
+    None
+}
+
+// `UTF-8''na%C3%AFve.txt` from a `filename*` parameter (RFC 8187)
+fn decode_extended(value: &str) -> String {
+    match value.splitn(3, '\'').nth(2) {
+        Some(encoded) => urlencoded::decode(encoded),
+        None => value.to_string(),
+    }
+}
+
+// Browsers used to send full paths, keep only the last component
+fn basename(filename: &str) -> String {
+    filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+fn malformed(message: &str) -> HttpError {
+    rejection(StatusCode::BAD_REQUEST, message)
+}
+
+fn too_large(message: &str) -> HttpError {
+    rejection(StatusCode::CONTENT_TOO_LARGE, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hands out at most `step` bytes per read, like a slow connection
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buffer.len()).min(self.data.len());
+            buffer[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    const BODY: &str = "preamble to ignore\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        Hello, world\r\n\
+        --XyZ  \t\r\n\
+        Content-Disposition: form-data; name=\"upload\"; filename=\"C:\\\\dir\\\\notes.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\nline two --XyZ not a delimiter\r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    fn parse(body: &str, limits: FormLimits) -> Result<Multipart, HttpError> {
+        Multipart::parse(body.as_bytes(), "XyZ", limits)
+    }
+
+    fn status(result: Result<Multipart, HttpError>) -> u16 {
+        match result {
+            Ok(_) => panic!("expected the form to be rejected"),
+            Err(e) => e.status.as_u16(),
+        }
+    }
+
+    fn contents(file: &UploadedFile) -> String {
+        fs::read_to_string(file.path()).unwrap()
+    }
+
+    #[test]
+    fn fields_and_files() {
+        let form = parse(BODY, FormLimits::default()).unwrap();
+
+        assert_eq!(
+            form.fields,
+            [("title".to_string(), "Hello, world".to_string())]
+        );
+        let file = form.file("upload").unwrap();
+        assert_eq!(file.filename.as_deref(), Some("notes.txt"));
+        assert_eq!(file.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(contents(file), "line one\r\nline two --XyZ not a delimiter");
+        assert_eq!(file.size, 40);
+    }
+
+    // Every read size splits the delimiter somewhere for one of them
+    #[test]
+    fn delimiters_split_across_reads() {
+        for step in 1..=20 {
+            let reader = Trickle {
+                data: BODY.as_bytes(),
+                step,
+            };
+            let form = Multipart::parse(reader, "XyZ", FormLimits::default()).unwrap();
+
+            assert_eq!(form.field("title"), Some("Hello, world"), "step {}", step);
+            assert_eq!(
+                contents(form.file("upload").unwrap()),
+                "line one\r\nline two --XyZ not a delimiter",
+                "step {}",
+                step
+            );
+        }
+    }
+
+    #[test]
+    fn extended_filename_wins() {
+        let body = "--XyZ\r\n\
+            Content-Disposition: form-data; name=\"f\"; filename=\"fallback.txt\"; filename*=UTF-8''na%C3%AFve%20file.txt\r\n\
+            \r\n\
+            data\r\n\
+            --XyZ--";
+        let form = parse(body, FormLimits::default()).unwrap();
+
+        assert_eq!(
+            form.file("f").unwrap().filename.as_deref(),
+            Some("naïve file.txt")
+        );
+    }
+
+    #[test]
+    fn empty_filename_is_a_file_without_name() {
+        let body = "--XyZ\r\n\
+            Content-Disposition: form-data; name=\"f\"; filename=\"\"\r\n\
+            \r\n\
+            \r\n\
+            --XyZ--";
+        let form = parse(body, FormLimits::default()).unwrap();
+
+        let file = form.file("f").unwrap();
+        assert_eq!(file.filename, None);
+        assert_eq!(file.size, 0);
+    }
+
+    #[test]
+    fn limits() {
+        let limits = FormLimits::default();
+
+        let small_field = FormLimits {
+            max_field_size: 11,
+            ..limits
+        };
+        assert_eq!(status(parse(BODY, small_field)), 413);
+
+        let small_file = FormLimits {
+            max_file_size: 39,
+            ..limits
+        };
+        assert_eq!(status(parse(BODY, small_file)), 413);
+
+        let one_part = FormLimits {
+            max_parts: 1,
+            ..limits
+        };
+        assert_eq!(status(parse(BODY, one_part)), 413);
+
+        let small_total = FormLimits {
+            max_total_size: 100,
+            ..limits
+        };
+        assert_eq!(status(parse(BODY, small_total)), 413);
+
+        // Exactly at every limit is fine
+        let exact = FormLimits {
+            max_field_size: 12,
+            max_file_size: 40,
+            max_parts: 2,
+            max_total_size: BODY.len() as u64,
+        };
+        assert!(parse(BODY, exact).is_ok());
+    }
+
+    #[test]
+    fn malformed_bodies() {
+        let cases = [
+            "no boundary anywhere",
+            "--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nunterminated",
+            "--XyZ\r\nContent-Disposition: attachment; name=\"a\"\r\n\r\nx\r\n--XyZ--",
+            "--XyZ\r\nContent-Disposition: form-data\r\n\r\nx\r\n--XyZ--",
+            "--XyZjunk\r\n\r\nx\r\n--XyZ--",
+        ];
+
+        for body in cases {
+            assert_eq!(
+                status(parse(body, FormLimits::default())),
+                400,
+                "{:?}",
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn parameters() {
+        let header = "form-data; name=\"a;b\"; filename=\"say \\\"hi\\\".txt\"; size=3";
+
+        assert_eq!(parameter(header, "name").as_deref(), Some("a;b"));
+        assert_eq!(
+            parameter(header, "filename").as_deref(),
+            Some("say \"hi\".txt")
+        );
+        assert_eq!(parameter(header, "SIZE").as_deref(), Some("3"));
+        assert_eq!(parameter(header, "missing"), None);
+    }
+}
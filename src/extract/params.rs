@@ -0,0 +1,140 @@
+use super::{media_type, rejection, FormLimits, FromRequest, Multipart};
+use crate::{http::urlencoded, HttpError, HttpRequest, StatusCode};
+use std::{collections::HashMap, str::FromStr};
+
+// Builds a typed value from string fields such as the query string, path
+// parameters or a form body. Errors describe the offending field.
+pub trait FromParams: Sized {
+    fn from_params(params: &Params) -> Result<Self, String>;
+}
+
+// The first value of every field
+impl FromParams for HashMap<String, String> {
+    fn from_params(params: &Params) -> Result<Self, String> {
+        Ok(urlencoded::first_values(params.0))
+    }
+}
+
+// Every field in order, repeated ones included
+impl FromParams for Vec<(String, String)> {
+    fn from_params(params: &Params) -> Result<Self, String> {
+        Ok(params.0.to_vec())
+    }
+}
+
+// Named string fields with typed lookups. A field can appear more than once,
+// e.g. `?tag=a&tag=b`; `get` and friends see the first value.
+pub struct Params<'a>(pub &'a [(String, String)]);
+
+impl Params<'_> {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn required<T: FromStr>(&self, name: &str) -> Result<T, String> {
+        match self.get(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("invalid value for `{}`", name)),
+            None => Err(format!("missing field `{}`", name)),
+        }
+    }
+
+    pub fn optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.get(name) {
+            Some(_) => self.required(name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Every value of a repeated field, none at all is an empty Vec
+    pub fn all<T: FromStr>(&self, name: &str) -> Result<Vec<T>, String> {
+        self.get_all(name)
+            .into_iter()
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value for `{}`", name))
+            })
+            .collect()
+    }
+}
+
+// The query string, rejected with 400 if it doesn't fit `T`
+pub struct Query<T>(pub T);
+
+impl<T: FromParams> FromRequest for Query<T> {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        T::from_params(&Params(&request.query_pairs))
+            .map(Query)
+            .map_err(|e| rejection(StatusCode::BAD_REQUEST, &format!("Invalid query: {}", e)))
+    }
+}
+
+// The parameters captured by the route pattern, rejected with 400 if they don't fit `T`
+pub struct Path<T>(pub T);
+
+impl<T: FromParams> FromRequest for Path<T> {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        let params: Vec<(String, String)> = request
+            .params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        T::from_params(&Params(&params))
+            .map(Path)
+            .map_err(|e| rejection(StatusCode::BAD_REQUEST, &format!("Invalid path: {}", e)))
+    }
+}
+
+// An `application/x-www-form-urlencoded` or `multipart/form-data` body, only
+// the text fields of multipart forms are used (see `Multipart` for files).
+// Other content types get a 415, bodies over the `FormLimits` a 413 and forms
+// that don't fit `T` a 422.
+pub struct Form<T>(pub T);
+
+impl<T: FromParams> FromRequest for Form<T> {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        let fields = match media_type(request).as_deref() {
+            Some("application/x-www-form-urlencoded") => {
+                if request.body.len() as u64 > FormLimits::of(request).max_total_size {
+                    return Err(rejection(
+                        StatusCode::CONTENT_TOO_LARGE,
+                        "Form body too large",
+                    ));
+                }
+
+                let body = request.body_text().ok_or_else(|| {
+                    rejection(StatusCode::BAD_REQUEST, "Form body is not valid UTF-8")
+                })?;
+                urlencoded::parse(body)
+            }
+            Some("multipart/form-data") => Multipart::from_request(request)?.fields,
+            _ => {
+                return Err(rejection(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    "Expected an application/x-www-form-urlencoded or multipart/form-data body",
+                ))
+            }
+        };
+
+        T::from_params(&Params(&fields)).map(Form).map_err(|e| {
+            rejection(
+                StatusCode::UNPROCESSABLE_CONTENT,
+                &format!("Invalid form: {}", e),
+            )
+        })
+    }
+}
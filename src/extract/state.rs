@@ -0,0 +1,30 @@
+use super::FromRequest;
+use crate::{HttpError, HttpRequest, StatusCode};
+use std::{ops::Deref, sync::Arc};
+
+// Shared application state registered with `Router::state`
+pub struct State<T>(pub Arc<T>);
+
+impl<T: Send + Sync + 'static> FromRequest for State<T> {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        match request.extensions.get_arc::<T>() {
+            Some(state) => Ok(State(state)),
+            None => {
+                // A handler asking for state nobody registered is a bug on our side
+                println!(
+                    "No state of type {} registered on the router",
+                    std::any::type_name::<T>()
+                );
+                Err(HttpError::new(StatusCode::INTERNAL_SERVER_ERROR))
+            }
+        }
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    fmt::{self, Debug},
+    fs::File,
+    io::{self, Read, Write},
+};
+
+// Size of the pieces streamed bodies are read and written in
+pub const STREAM_CHUNK_SIZE: usize = 16 * 1024;
+
+#[derive(Default)]
+pub enum Body {
+    #[default]
+    Empty,
+    Bytes(Vec<u8>),
+    Reader {
+        reader: Box<dyn Read + Send>,
+        length: Option<u64>,
+    },
+    Chunks(Box<dyn Iterator<Item = Vec<u8>> + Send>),
+}
+
+impl Body {
+    // Streams a file from disk, taking the length from its metadata
+    pub fn from_file(file: File) -> io::Result<Body> {
+        let length = file.metadata()?.len();
+        Ok(Body::from_reader(file, Some(length)))
+    }
+
+    pub fn from_reader<R: Read + Send + 'static>(reader: R, length: Option<u64>) -> Body {
+        Body::Reader {
+            reader: Box::new(reader),
+            length,
+        }
+    }
+
+    pub fn from_chunks<I>(chunks: I) -> Body
+    where
+        I: Iterator<Item = Vec<u8>> + Send + 'static,
+    {
+        Body::Chunks(Box::new(chunks))
+    }
+
+    // The size of the body in bytes, if it is known up front
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Body::Empty => Some(0),
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Reader { length, .. } => *length,
+            Body::Chunks(_) => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    // The in-memory contents, or None for streaming bodies
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Empty => Some(&[]),
+            Body::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    // Turns any body into a reader of its bytes, e.g. to transform it on the way out
+    pub fn into_reader(self) -> Box<dyn Read + Send> {
+        match self {
+            Body::Empty => Box::new(io::empty()),
+            Body::Bytes(bytes) => Box::new(io::Cursor::new(bytes)),
+            Body::Reader {
+                reader,
+                length: Some(length),
+            } => Box::new(reader.take(length)),
+            Body::Reader { reader, .. } => reader,
+            Body::Chunks(chunks) => Box::new(ChunksReader {
+                chunks,
+                current: io::Cursor::new(Vec::new()),
+            }),
+        }
+    }
+
+    // Writes the body out, framing every piece as an HTTP/1.1 chunk when `chunked` is set
+    pub fn write_to<W: Write>(self, writer: &mut W, chunked: bool) -> io::Result<()> {
+        match self {
+            Body::Empty => {}
+            Body::Bytes(bytes) => write_piece(writer, &bytes, chunked)?,
+            Body::Reader { mut reader, length } => {
+                // Never send more than we announced in Content-Length
+                let mut remaining = length.unwrap_or(u64::MAX);
+                let mut buffer = vec![0; STREAM_CHUNK_SIZE];
+
+                while remaining > 0 {
+                    let wanted = buffer.len().min(remaining as usize);
+                    let read = match reader.read(&mut buffer[..wanted]) {
+                        Ok(0) => break,
+                        Ok(read) => read,
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    };
+
+                    write_piece(writer, &buffer[..read], chunked)?;
+                    remaining -= read as u64;
+                }
+
+                if length.is_some() && remaining > 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "body ended before its announced length",
+                    ));
+                }
+            }
+            Body::Chunks(chunks) => {
+                for chunk in chunks {
+                    write_piece(writer, &chunk, chunked)?;
+                }
+            }
+        }
+
+        if chunked {
+            writer.write_all(b"0\r\n\r\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_piece<W: Write>(writer: &mut W, piece: &[u8], chunked: bool) -> io::Result<()> {
+    if !chunked {
+        return writer.write_all(piece);
+    }
+
+    // A zero sized chunk would terminate the body early
+    if piece.is_empty() {
+        return Ok(());
+    }
+
+    write!(writer, "{:X}\r\n", piece.len())?;
+    writer.write_all(piece)?;
+    writer.write_all(b"\r\n")
+}
+
+struct ChunksReader {
+    chunks: Box<dyn Iterator<Item = Vec<u8>> + Send>,
+    current: io::Cursor<Vec<u8>>,
+}
+
+impl Read for ChunksReader {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buffer)?;
+            if read > 0 || buffer.is_empty() {
+                return Ok(read);
+            }
+
+            match self.chunks.next() {
+                Some(chunk) => self.current = io::Cursor::new(chunk),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Body::Bytes(bytes)
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Body::Bytes(text.into_bytes())
+    }
+}
+
+impl From<&str> for Body {
+    fn from(text: &str) -> Self {
+        Body::Bytes(text.as_bytes().to_vec())
+    }
+}
+
+impl Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Empty => write!(f, "Body::Empty"),
+            Body::Bytes(bytes) => write!(f, "Body::Bytes({} bytes)", bytes.len()),
+            Body::Reader { length, .. } => write!(f, "Body::Reader({:?} bytes)", length),
+            Body::Chunks(_) => write!(f, "Body::Chunks"),
+        }
+    }
+}
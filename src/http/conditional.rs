@@ -0,0 +1,250 @@
+use super::{date, HttpMethod, HttpRequest, HttpResponse, StatusCode};
+use crate::crypto::{base64, sha256::sha256};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::Metadata,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// An entity tag identifying one version of a resource. Strong tags promise
+// byte-identical content, weak ones only equivalent content.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ETag {
+    pub weak: bool,
+    // The opaque tag without quotes
+    pub tag: String,
+}
+
+impl ETag {
+    // Panics if the tag contains a quote or other characters ETags can't carry
+    pub fn strong(tag: &str) -> ETag {
+        assert!(valid_tag(tag), "invalid entity tag: {}", tag);
+        ETag {
+            weak: false,
+            tag: tag.to_string(),
+        }
+    }
+
+    pub fn weak(tag: &str) -> ETag {
+        ETag {
+            weak: true,
+            ..ETag::strong(tag)
+        }
+    }
+
+    // A strong tag hashed from the full content, for dynamic responses
+    pub fn from_bytes(content: &[u8]) -> ETag {
+        ETag::strong(&base64::encode(&sha256(content)[..16]))
+    }
+
+    // A strong tag from a file's size and modification time, like most servers use
+    pub fn from_metadata(metadata: &Metadata) -> ETag {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos());
+        ETag::strong(&format!("{:x}-{:x}", metadata.len(), modified))
+    }
+
+    // Parses `"tag"` or `W/"tag"`
+    pub fn parse(value: &str) -> Option<ETag> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, value),
+        };
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+
+        valid_tag(tag).then(|| ETag {
+            weak,
+            tag: tag.to_string(),
+        })
+    }
+
+    // Both tags are strong and identical, used by If-Match
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    // The tags are identical ignoring weakness, used by If-None-Match
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl Display for ETag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+fn valid_tag(tag: &str) -> bool {
+    tag.bytes()
+        .all(|b| b == 0x21 || (0x23..=0x7e).contains(&b) || b >= 0x80)
+}
+
+// The validators of the current version of a resource, used to answer
+// conditional requests (RFC 9110 section 13). Handlers check a request
+// before doing the work and add the validators to the full response:
+//
+//     let conditional = Conditional::new().etag(ETag::from_bytes(&page));
+//     if let Some(response) = conditional.check(&request) {
+//         return response;
+//     }
+//     conditional.apply(&mut response);
+#[derive(Clone, Default)]
+pub struct Conditional {
+    pub etag: Option<ETag>,
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Conditional {
+    pub fn new() -> Conditional {
+        Conditional::default()
+    }
+
+    // Validators for a file, from its metadata
+    pub fn from_metadata(metadata: &Metadata) -> Conditional {
+        Conditional {
+            etag: Some(ETag::from_metadata(metadata)),
+            last_modified: metadata.modified().ok(),
+        }
+    }
+
+    pub fn etag(mut self, etag: ETag) -> Conditional {
+        self.etag = Some(etag);
+        self
+    }
+
+    pub fn last_modified(mut self, time: SystemTime) -> Conditional {
+        self.last_modified = Some(time);
+        self
+    }
+
+    // Evaluates the request's preconditions in the order RFC 9110 section 13.2.2
+    // gives. Returns the 304 or 412 to answer with, or None if the request
+    // should be handled normally.
+    pub fn check(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        let safe = matches!(request.method, HttpMethod::GET | HttpMethod::HEAD);
+        let last_modified = self.last_modified.map(truncate);
+
+        if let Some(if_match) = header_list(request, "If-Match") {
+            if !self.matches(&if_match, ETag::strong_eq) {
+                return Some(self.precondition_failed());
+            }
+        } else if let Some(since) = request.header("If-Unmodified-Since").and_then(date::parse) {
+            if last_modified.is_some_and(|modified| modified > since) {
+                return Some(self.precondition_failed());
+            }
+        }
+
+        if let Some(if_none_match) = header_list(request, "If-None-Match") {
+            if self.matches(&if_none_match, ETag::weak_eq) {
+                return Some(match safe {
+                    true => self.not_modified(),
+                    false => self.precondition_failed(),
+                });
+            }
+        } else if let Some(since) = request.header("If-Modified-Since").and_then(date::parse) {
+            if safe && last_modified.is_some_and(|modified| modified <= since) {
+                return Some(self.not_modified());
+            }
+        }
+
+        None
+    }
+
+    // Whether a Range header may be honored: without If-Range always, otherwise
+    // only if the client's copy is still current. Weak tags never match.
+    pub fn if_range(&self, request: &HttpRequest) -> bool {
+        let Some(value) = request.header("If-Range") else {
+            return true;
+        };
+
+        match (ETag::parse(value), date::parse(value)) {
+            (Some(etag), _) => self
+                .etag
+                .as_ref()
+                .is_some_and(|current| etag.strong_eq(current)),
+            (_, Some(since)) => self.last_modified.map(truncate) == Some(since),
+            _ => false,
+        }
+    }
+
+    // Adds the ETag and Last-Modified headers to a response
+    pub fn apply(&self, response: &mut HttpResponse) {
+        if let Some(etag) = &self.etag {
+            response.headers.insert("ETag", &etag.to_string());
+        }
+        if let Some(time) = self.last_modified {
+            response
+                .headers
+                .insert("Last-Modified", &date::format(time));
+        }
+    }
+
+    // `*` matches any current version, and there is one if we were asked.
+    // Otherwise one of the listed tags has to.
+    fn matches(&self, list: &str, eq: fn(&ETag, &ETag) -> bool) -> bool {
+        if list.trim() == "*" {
+            return true;
+        }
+
+        let Some(current) = &self.etag else {
+            return false;
+        };
+        split_etags(list)
+            .filter_map(ETag::parse)
+            .any(|etag| eq(&etag, current))
+    }
+
+    fn not_modified(&self) -> HttpResponse {
+        let mut response = HttpResponse::new(StatusCode::NOT_MODIFIED);
+        self.apply(&mut response);
+        response
+    }
+
+    fn precondition_failed(&self) -> HttpResponse {
+        HttpResponse::new(StatusCode::PRECONDITION_FAILED)
+    }
+}
+
+// All values of a list header joined, None if the header is missing
+fn header_list(request: &HttpRequest, name: &str) -> Option<String> {
+    let values = request.headers.get_all(name);
+    (!values.is_empty()).then(|| values.join(","))
+}
+
+// Splits a list of entity tags on the commas between them, tags may contain commas
+fn split_etags(list: &str) -> impl Iterator<Item = &str> {
+    let mut rest = list;
+
+    std::iter::from_fn(move || {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        if rest.is_empty() {
+            return None;
+        }
+
+        let start = if rest.starts_with("W/") { 2 } else { 0 };
+        let end = match rest[start..].strip_prefix('"') {
+            Some(quoted) => quoted.find('"').map_or(rest.len(), |i| start + i + 2),
+            None => rest.find(',').unwrap_or(rest.len()),
+        };
+
+        let (etag, remainder) = rest.split_at(end);
+        rest = remainder;
+        Some(etag)
+    })
+}
+
+// HTTP dates have whole seconds, so file times are compared at that precision
+fn truncate(time: SystemTime) -> SystemTime {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
@@ -0,0 +1,211 @@
+use super::{
+    http_response::SERVER, HttpMethod, HttpRequest, HttpResponse, HttpVersion, ParseError,
+    ParserLimits, RequestParser, WriteOptions,
+};
+use crate::threading::panic;
+use std::{
+    io::{self, BufWriter, Write},
+    net::TcpStream,
+    panic::{catch_unwind, AssertUnwindSafe},
+    time::Duration,
+};
+
+pub struct KeepAlive {
+    pub timeout: Duration,
+    pub max_requests: usize,
+}
+
+impl Default for KeepAlive {
+    fn default() -> KeepAlive {
+        KeepAlive {
+            timeout: Duration::from_secs(5),
+            max_requests: 100,
+        }
+    }
+}
+
+pub struct Connection {
+    stream: TcpStream,
+    limits: ParserLimits,
+    keep_alive: KeepAlive,
+    server: Option<String>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Connection {
+        Connection::with_config(stream, ParserLimits::default(), KeepAlive::default())
+    }
+
+    pub fn with_config(
+        stream: TcpStream,
+        limits: ParserLimits,
+        keep_alive: KeepAlive,
+    ) -> Connection {
+        Connection {
+            stream,
+            limits,
+            keep_alive,
+            server: Some(SERVER.to_string()),
+        }
+    }
+
+    // The `Server` header sent with every response, None leaves it out
+    pub fn server(mut self, server: Option<&str>) -> Connection {
+        self.server = server.map(str::to_string);
+        self
+    }
+
+    // Serves requests from the socket until the client or the keep-alive policy closes it.
+    // Pipelined requests are answered in the order they were received.
+    pub fn serve<F>(self, handler: F)
+    where
+        F: Fn(HttpRequest) -> HttpResponse,
+    {
+        let Connection {
+            stream,
+            limits,
+            keep_alive,
+            server,
+        } = self;
+
+        // The timeout bounds how long an idle connection can hold on to a worker
+        if stream.set_read_timeout(Some(keep_alive.timeout)).is_err() {
+            return;
+        }
+
+        let mut parser = RequestParser::with_limits(&stream, limits);
+        let mut writer = &stream;
+        let mut served = 0;
+
+        loop {
+            let request = match Self::read_request(&mut parser, writer) {
+                Ok(Some(request)) => request,
+                Ok(None) => return,
+                Err(ParseError::Io(_)) | Err(ParseError::UnexpectedEof) => return,
+                Err(e) => {
+                    println!("Rejecting malformed request: {}", e);
+
+                    let mut response = HttpResponse::from(e.status_code());
+                    response.headers.insert("Connection", "close");
+                    let options = WriteOptions {
+                        head: false,
+                        chunked: false,
+                        server: server.clone(),
+                    };
+                    let _ = Self::write_response(&mut writer, response, &options);
+                    return;
+                }
+            };
+
+            served += 1;
+            let mut persistent =
+                Self::wants_keep_alive(&request) && served < keep_alive.max_requests;
+            let version = request.version.clone();
+            let head = request.method == HttpMethod::HEAD;
+            let context = format!(
+                "request {} ({} {})",
+                request.id, request.method, request.path
+            );
+
+            // The handler may have left shared state half updated, so the
+            // connection is closed after answering with a 500
+            let mut response = match catch_unwind(AssertUnwindSafe(|| handler(request))) {
+                Ok(response) => response,
+                Err(payload) => {
+                    println!(
+                        "Handler panicked on {}: {}",
+                        context,
+                        panic::message(&*payload)
+                    );
+                    persistent = false;
+                    HttpResponse::internal_server_error()
+                }
+            };
+            if response.headers.has_token("Connection", "close") {
+                persistent = false;
+            }
+
+            // HTTP/1.0 has no chunked encoding, so a body of unknown size can
+            // only be delimited by closing the connection
+            let chunked = !matches!(version, HttpVersion::HTTP10);
+            if !chunked && response.body.len().is_none() {
+                persistent = false;
+            }
+
+            if !persistent {
+                response.headers.insert("Connection", "close");
+            } else if let HttpVersion::HTTP10 = version {
+                // HTTP/1.0 clients only keep the connection open when told to
+                response.headers.insert("Connection", "keep-alive");
+                response.headers.insert(
+                    "Keep-Alive",
+                    &format!(
+                        "timeout={}, max={}",
+                        keep_alive.timeout.as_secs(),
+                        keep_alive.max_requests - served
+                    ),
+                );
+            }
+
+            let options = WriteOptions {
+                head,
+                chunked,
+                server: server.clone(),
+            };
+            if Self::write_response(&mut writer, response, &options).is_err() || !persistent {
+                return;
+            }
+        }
+    }
+
+    fn read_request(
+        parser: &mut RequestParser<&TcpStream>,
+        mut stream: &TcpStream,
+    ) -> Result<Option<HttpRequest>, ParseError> {
+        let mut request = match parser.read_head()? {
+            Some(request) => request,
+            None => return Ok(None),
+        };
+
+        // A body we would refuse is refused before the client starts sending it
+        parser.check_body_length(&request)?;
+
+        if request.expects_continue() {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+            stream.flush()?;
+        } else if request.header("Expect").is_some() && !Self::ignores_continue(&request) {
+            return Err(ParseError::ExpectationFailed);
+        }
+
+        parser.read_body(&mut request)?;
+        Ok(Some(request))
+    }
+
+    // HTTP/1.0 clients can't know 100-continue, so it must be ignored (RFC 9110 section 10.1.1)
+    fn ignores_continue(request: &HttpRequest) -> bool {
+        matches!(request.version, HttpVersion::HTTP10)
+            && request
+                .header("Expect")
+                .is_some_and(|value| value.eq_ignore_ascii_case("100-continue"))
+    }
+
+    fn wants_keep_alive(request: &HttpRequest) -> bool {
+        match request.version {
+            HttpVersion::HTTP10 => request.headers.has_token("Connection", "keep-alive"),
+            _ => !request.headers.has_token("Connection", "close"),
+        }
+    }
+
+    fn write_response(
+        stream: &mut &TcpStream,
+        mut response: HttpResponse,
+        options: &WriteOptions,
+    ) -> io::Result<()> {
+        // We only speak HTTP/1.1 on the wire, whatever the handler put in there
+        response.version = HttpVersion::HTTP11;
+
+        let mut writer = BufWriter::new(*stream);
+        response.write_with(&mut writer, options)?;
+        writer.flush()
+    }
+}
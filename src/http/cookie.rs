@@ -0,0 +1,374 @@
+use super::{date, header_map::is_token_char};
+use crate::crypto::{base64, constant_time_eq, hmac_sha256, random_bytes};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+// A cookie to send with `Set-Cookie`, built up with the attribute methods:
+// `Cookie::new("theme", "dark").path("/").max_age(Duration::from_secs(3600))`
+#[derive(Clone, Debug)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub path: Option<String>,
+    pub domain: Option<String>,
+    pub expires: Option<SystemTime>,
+    pub max_age: Option<Duration>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            domain: None,
+            expires: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    // A cookie telling the client to delete `name`. Path and domain must match
+    // the ones the cookie was set with.
+    pub fn removal(name: &str) -> Cookie {
+        Cookie::new(name, "")
+            .expires(UNIX_EPOCH)
+            .max_age(Duration::ZERO)
+    }
+
+    pub fn path(mut self, path: &str) -> Cookie {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> Cookie {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn expires(mut self, expires: SystemTime) -> Cookie {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Cookie {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Cookie {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Cookie {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Cookie {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    // Checks that the cookie can be sent as is. The name must be a token and the
+    // value cookie-octets, optionally in double quotes; path and domain can't
+    // contain `;` or control characters. Otherwise a value could add attributes
+    // of its own, e.g. `x; Domain=example.com`.
+    pub fn validate(&self) -> Result<(), CookieError> {
+        if self.name.is_empty() || !self.name.chars().all(is_token_char) {
+            return Err(CookieError::InvalidName(self.name.clone()));
+        }
+
+        let value = match self.value.strip_prefix('"') {
+            Some(quoted) => quoted.strip_suffix('"'),
+            None => Some(self.value.as_str()),
+        };
+        if !value.is_some_and(|value| value.bytes().all(is_cookie_octet)) {
+            return Err(CookieError::InvalidValue(self.name.clone()));
+        }
+
+        for (attribute, value) in [("Path", &self.path), ("Domain", &self.domain)] {
+            if value
+                .as_deref()
+                .is_some_and(|value| value.contains(|c: char| c == ';' || c.is_control()))
+            {
+                return Err(CookieError::InvalidAttribute(attribute));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Printable ASCII except whitespace, `"`, `,`, `;` and `\` (RFC 6265)
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+pub enum CookieError {
+    InvalidName(String),
+    InvalidValue(String),
+    InvalidAttribute(&'static str),
+}
+
+impl Display for CookieError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieError::InvalidName(name) => write!(f, "invalid cookie name `{}`", name),
+            CookieError::InvalidValue(name) => write!(
+                f,
+                "invalid value for cookie `{}`: only cookie-octets are allowed",
+                name
+            ),
+            CookieError::InvalidAttribute(attribute) => write!(
+                f,
+                "invalid cookie {}: contains `;` or a control character",
+                attribute
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for CookieError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CookieError({})", self)
+    }
+}
+
+impl std::error::Error for CookieError {}
+
+// The `Set-Cookie` value. Browsers drop `SameSite=None` cookies that aren't
+// `Secure`, so those are always marked secure. Nothing is escaped here, see
+// `Cookie::validate`.
+impl Display for Cookie {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", date::format(expires))?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.secure || self.same_site == Some(SameSite::None) {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict"),
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax"),
+            Some(SameSite::None) => write!(f, "; SameSite=None"),
+            None => Ok(()),
+        }
+    }
+}
+
+// The cookies a request was sent with, by name
+#[derive(Clone, Default, Debug)]
+pub struct Cookies(pub HashMap<String, String>);
+
+impl Cookies {
+    // Parses `Cookie` header values such as `a=1; b="2"`. Pairs without `=` are skipped.
+    pub fn parse<'a>(headers: impl IntoIterator<Item = &'a str>) -> Cookies {
+        let mut cookies = HashMap::new();
+
+        for header in headers {
+            for (name, value) in header
+                .split(';')
+                .filter_map(|pair| pair.trim().split_once('='))
+            {
+                cookies
+                    .entry(name.trim().to_string())
+                    .or_insert_with(|| value.trim().trim_matches('"').to_string());
+            }
+        }
+
+        Cookies(cookies)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(|value| value.as_str())
+    }
+
+    // Cookies signed with `key`, see `Key::sign`
+    pub fn signed<'a>(&'a self, key: &'a Key) -> SignedJar<'a> {
+        SignedJar { cookies: self, key }
+    }
+
+    // Cookies encrypted with `key`, see `Key::encrypt`
+    pub fn private<'a>(&'a self, key: &'a Key) -> PrivateJar<'a> {
+        PrivateJar { cookies: self, key }
+    }
+}
+
+const TAG_LEN: usize = 32;
+const NONCE_LEN: usize = 16;
+
+// Secret for signing and encrypting cookies, derived from a server secret of at
+// least 32 bytes. Every server sharing cookies needs the same secret.
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+    authentication: [u8; 32],
+}
+
+impl Key {
+    pub fn new(secret: &[u8]) -> Key {
+        assert!(
+            secret.len() >= 32,
+            "cookie secret must be at least 32 bytes, got {}",
+            secret.len()
+        );
+
+        // Separate keys per purpose so one can't be used to attack another
+        Key {
+            signing: hmac_sha256(secret, b"cookie signing"),
+            encryption: hmac_sha256(secret, b"cookie encryption"),
+            authentication: hmac_sha256(secret, b"cookie authentication"),
+        }
+    }
+
+    // A random key, cookies signed with it don't survive a restart. Panics if
+    // the operating system's random source can't be read.
+    pub fn generate() -> Key {
+        match random_bytes(64) {
+            Ok(secret) => Key::new(&secret),
+            Err(e) => panic!("failed to generate a cookie key: {}", e),
+        }
+    }
+
+    // Prefixes the value with a MAC over name and value. The value stays readable
+    // by the client but can't be changed without the key.
+    pub fn sign(&self, mut cookie: Cookie) -> Cookie {
+        let tag = hmac_sha256(&self.signing, &signed_message(&cookie.name, &cookie.value));
+        cookie.value = format!("{}{}", base64::encode(&tag), cookie.value);
+        cookie
+    }
+
+    // Encrypts the value so the client can neither read nor change it. The cipher
+    // is HMAC-SHA256 in counter mode under a random nonce, followed by a MAC over
+    // name, nonce and ciphertext (encrypt-then-MAC). Panics if the operating
+    // system's random source can't be read, a predictable nonce would leak
+    // the keystream.
+    pub fn encrypt(&self, mut cookie: Cookie) -> Cookie {
+        let nonce = match random_bytes(NONCE_LEN) {
+            Ok(nonce) => nonce,
+            Err(e) => panic!("failed to generate a cookie nonce: {}", e),
+        };
+        let mut sealed = nonce.clone();
+        sealed.extend(self.keystream(&nonce, cookie.value.as_bytes()));
+
+        let tag = hmac_sha256(&self.authentication, &sealed_message(&cookie.name, &sealed));
+        sealed.extend_from_slice(&tag);
+
+        cookie.value = base64::encode(&sealed);
+        cookie
+    }
+
+    fn verify(&self, name: &str, value: &str) -> Option<String> {
+        let encoded_len = base64::encode(&[0; TAG_LEN]).len();
+        let tag = value.get(..encoded_len)?;
+        let value = &value[encoded_len..];
+        let expected = hmac_sha256(&self.signing, &signed_message(name, value));
+
+        match constant_time_eq(&base64::decode(tag)?, &expected) {
+            true => Some(value.to_string()),
+            false => None,
+        }
+    }
+
+    fn decrypt(&self, name: &str, value: &str) -> Option<String> {
+        let sealed = base64::decode(value)?;
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+
+        let (sealed, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let expected = hmac_sha256(&self.authentication, &sealed_message(name, sealed));
+        if !constant_time_eq(tag, &expected) {
+            return None;
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        String::from_utf8(self.keystream(nonce, ciphertext)).ok()
+    }
+
+    // XORs `data` with HMAC(encryption key, nonce || block counter) blocks
+    fn keystream(&self, nonce: &[u8], data: &[u8]) -> Vec<u8> {
+        data.chunks(32)
+            .enumerate()
+            .flat_map(|(counter, chunk)| {
+                let mut input = nonce.to_vec();
+                input.extend_from_slice(&(counter as u64).to_be_bytes());
+                let block = hmac_sha256(&self.encryption, &input);
+
+                chunk
+                    .iter()
+                    .zip(block)
+                    .map(|(byte, key)| byte ^ key)
+                    .collect::<Vec<u8>>()
+            })
+            .collect()
+    }
+}
+
+// The name is part of the MAC so a value can't be moved to another cookie
+fn signed_message(name: &str, value: &str) -> Vec<u8> {
+    format!("{}={}", name, value).into_bytes()
+}
+
+fn sealed_message(name: &str, sealed: &[u8]) -> Vec<u8> {
+    let mut message = name.as_bytes().to_vec();
+    message.push(b'=');
+    message.extend_from_slice(sealed);
+    message
+}
+
+// Read access to signed cookies, values that fail verification are treated as missing
+pub struct SignedJar<'a> {
+    cookies: &'a Cookies,
+    key: &'a Key,
+}
+
+impl SignedJar<'_> {
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.key.verify(name, self.cookies.get(name)?)
+    }
+}
+
+// Read access to encrypted cookies, values that fail to decrypt are treated as missing
+pub struct PrivateJar<'a> {
+    cookies: &'a Cookies,
+    key: &'a Key,
+}
+
+impl PrivateJar<'_> {
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.key.decrypt(name, self.cookies.get(name)?)
+    }
+}
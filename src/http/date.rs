@@ -0,0 +1,120 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// Formats a time as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`,
+// the only format HTTP senders may use (RFC 9110 section 5.6.7)
+pub fn format(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let days = secs / 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+// Parses any of the three date formats recipients must accept: IMF-fixdate,
+// the obsolete RFC 850 format and asctime. None for anything else.
+pub fn parse(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+
+    match parts[..] {
+        // `Sun, 06 Nov 1994 08:49:37 GMT`
+        [weekday, day, month, year, time, "GMT"] if weekday.ends_with(',') && year.len() == 4 => {
+            from_parts(day, month, year.parse().ok()?, time)
+        }
+        // `Sunday, 06-Nov-94 08:49:37 GMT`
+        [weekday, date, time, "GMT"] if weekday.ends_with(',') => {
+            let mut fields = date.split('-');
+            let (day, month, year) = (fields.next()?, fields.next()?, fields.next()?);
+            if year.len() != 2 || fields.next().is_some() {
+                return None;
+            }
+
+            // Two-digit years more than 50 years ahead are in the last century
+            let (this_year, _, _) = civil_from_days(days_since_epoch(SystemTime::now()));
+            let mut year = this_year / 100 * 100 + year.parse::<i64>().ok()?;
+            if year > this_year + 50 {
+                year -= 100;
+            }
+            from_parts(day, month, year, time)
+        }
+        // `Sun Nov  6 08:49:37 1994`
+        [_, month, day, time, year] if year.len() == 4 => {
+            from_parts(day, month, year.parse().ok()?, time)
+        }
+        _ => None,
+    }
+}
+
+fn days_since_epoch(time: SystemTime) -> i64 {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    (secs / 86400) as i64
+}
+
+fn from_parts(day: &str, month: &str, year: i64, time: &str) -> Option<SystemTime> {
+    let day: u32 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+
+    let mut fields = time.split(':');
+    let mut field = |max: u64| {
+        fields
+            .next()
+            .filter(|field| field.len() == 2)
+            .and_then(|field| field.parse::<u64>().ok())
+            .filter(|value| *value <= max)
+    };
+    // 60 allows for a leap second
+    let (hour, minute, second) = (field(23)?, field(59)?, field(60)?);
+
+    let days = days_from_civil(year, month, day);
+    if days < 0 || civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    let secs = days as u64 * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// Converts days since 1970-01-01 to a (year, month, day) date in the
+// proleptic Gregorian calendar, after Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+// The inverse of `civil_from_days`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
@@ -0,0 +1,115 @@
+use super::{HttpResponse, ParseError, StatusCode};
+use crate::TemplateError;
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+    io,
+};
+
+// An error a handler can return with `?`, answered with its status and the router's error page.
+// The message is shown on that page, the source is only logged.
+pub struct HttpError {
+    pub status: StatusCode,
+    pub message: Option<String>,
+    pub source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl HttpError {
+    pub fn new(status: StatusCode) -> HttpError {
+        HttpError {
+            status,
+            message: None,
+            source: None,
+        }
+    }
+
+    pub fn with_message(mut self, message: &str) -> HttpError {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    pub fn with_source<E: Error + Send + Sync + 'static>(mut self, source: E) -> HttpError {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn bad_request(message: &str) -> HttpError {
+        HttpError::new(StatusCode::BAD_REQUEST).with_message(message)
+    }
+
+    pub fn forbidden() -> HttpError {
+        HttpError::new(StatusCode::FORBIDDEN)
+    }
+
+    pub fn not_found() -> HttpError {
+        HttpError::new(StatusCode::NOT_FOUND)
+    }
+
+    pub fn internal<E: Error + Send + Sync + 'static>(source: E) -> HttpError {
+        HttpError::new(StatusCode::INTERNAL_SERVER_ERROR).with_source(source)
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        // Client errors are expected, only failures on our side are worth logging
+        if let (true, Some(source)) = (self.status.is_server_error(), &self.source) {
+            println!("Handler failed with {}: {}", self.status, source);
+        }
+
+        let mut response = HttpResponse::new(self.status);
+        response.message = self.message;
+        response
+    }
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.message, &self.source) {
+            (Some(message), _) => write!(f, "{}: {}", self.status, message),
+            (None, Some(source)) => write!(f, "{}: {}", self.status, source),
+            (None, None) => write!(f, "{}", self.status),
+        }
+    }
+}
+
+impl Debug for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HttpError({})", self)
+    }
+}
+
+impl Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn Error + 'static))
+    }
+}
+
+impl From<StatusCode> for HttpError {
+    fn from(status: StatusCode) -> HttpError {
+        HttpError::new(status)
+    }
+}
+
+// Missing files are the client's problem, anything else is ours
+impl From<io::Error> for HttpError {
+    fn from(e: io::Error) -> HttpError {
+        match e.kind() {
+            io::ErrorKind::NotFound => HttpError::not_found(),
+            io::ErrorKind::PermissionDenied => HttpError::forbidden(),
+            _ => HttpError::internal(e),
+        }
+    }
+}
+
+impl From<TemplateError> for HttpError {
+    fn from(e: TemplateError) -> HttpError {
+        HttpError::internal(e)
+    }
+}
+
+impl From<ParseError> for HttpError {
+    fn from(e: ParseError) -> HttpError {
+        HttpError::new(e.status_code()).with_message(&e.to_string())
+    }
+}
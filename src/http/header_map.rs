@@ -0,0 +1,216 @@
+use super::ContentType;
+use std::fmt::{self, Debug, Display};
+
+// HTTP headers: names compare case-insensitively, a name can carry several
+// values (e.g. Set-Cookie) and iteration follows insertion order. Names must be
+// tokens and values can't contain CR, LF or NUL, which would let a value smuggle
+// in extra headers.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+pub enum HeaderError {
+    InvalidName(String),
+    InvalidValue(String),
+}
+
+impl HeaderMap {
+    pub fn new() -> HeaderMap {
+        HeaderMap::default()
+    }
+
+    // The first value for `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    // Replaces every value for `name`. Panics on names or values that aren't
+    // allowed in a header, see `try_insert`.
+    pub fn insert(&mut self, name: &str, value: &str) {
+        if let Err(e) = self.try_insert(name, value) {
+            panic!("{}", e);
+        }
+    }
+
+    // Adds a value for `name` after any existing ones. Panics on names or
+    // values that aren't allowed in a header, see `try_append`.
+    pub fn append(&mut self, name: &str, value: &str) {
+        if let Err(e) = self.try_append(name, value) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_insert(&mut self, name: &str, value: &str) -> Result<(), HeaderError> {
+        validate(name, value)?;
+
+        // Keep the position of the first value so output order stays stable
+        match self
+            .entries
+            .iter()
+            .position(|(key, _)| key.eq_ignore_ascii_case(name))
+        {
+            Some(index) => {
+                self.entries[index].1 = value.to_string();
+                let mut seen = 0;
+                self.entries.retain(|(key, _)| {
+                    if key.eq_ignore_ascii_case(name) {
+                        seen += 1;
+                        seen == 1
+                    } else {
+                        true
+                    }
+                });
+            }
+            None => self.entries.push((name.to_string(), value.to_string())),
+        }
+
+        Ok(())
+    }
+
+    pub fn try_append(&mut self, name: &str, value: &str) -> Result<(), HeaderError> {
+        validate(name, value)?;
+
+        self.entries.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    // Removes every value for `name`, returning the first one
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let first = self.get(name).map(|value| value.to_string());
+        self.entries
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+        first
+    }
+
+    // Headers in insertion order, one item per value
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    // Number of values, counting repeated names once per value
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Checks comma separated values such as `Connection: keep-alive, Upgrade` for a
+    // token, across every value for `name`
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.get_all(name).iter().any(|value| {
+            value
+                .split(',')
+                .any(|part| part.trim().eq_ignore_ascii_case(token))
+        })
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.get("Content-Length")
+            .and_then(|value| value.trim().parse().ok())
+    }
+
+    pub fn set_content_length(&mut self, length: u64) {
+        self.insert("Content-Length", &length.to_string());
+    }
+
+    // The media type without parameters such as charset
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.get("Content-Type")
+            .map(|value| ContentType::new(value.split(';').next().unwrap_or("")))
+    }
+
+    pub fn set_content_type(&mut self, content_type: ContentType) {
+        self.insert("Content-Type", &content_type.to_string());
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.get("Host")
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.get("Location")
+    }
+}
+
+// Header names are RFC 9110 tokens
+pub(crate) fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+fn validate(name: &str, value: &str) -> Result<(), HeaderError> {
+    if name.is_empty() || !name.chars().all(is_token_char) {
+        return Err(HeaderError::InvalidName(name.to_string()));
+    }
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(HeaderError::InvalidValue(name.to_string()));
+    }
+
+    Ok(())
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl IntoIterator for HeaderMap {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl Debug for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::InvalidName(name) => write!(f, "invalid header name `{}`", name),
+            HeaderError::InvalidValue(name) => {
+                write!(
+                    f,
+                    "invalid value for header `{}`: contains CR, LF or NUL",
+                    name
+                )
+            }
+        }
+    }
+}
+
+impl Debug for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HeaderError({})", self)
+    }
+}
+
+impl std::error::Error for HeaderError {}
@@ -1,81 +1,109 @@
-use super::{HttpMethod, HttpVersion};
+use super::{Cookies, Extensions, HeaderMap, HttpMethod, HttpVersion, SpooledBody};
 use std::{
     collections::HashMap,
     fmt::{Debug, Display},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone)]
 pub struct HttpRequest {
+    // Unique per process, used to correlate logs and error pages
+    pub id: u64,
     pub method: HttpMethod,
     pub path: String,
+    pub params: HashMap<String, String>,
+    // Decoded query parameters, the first value where a key repeats
     pub query: HashMap<String, String>,
+    // Every decoded query parameter in order, repeated keys included
+    pub query_pairs: Vec<(String, String)>,
     pub version: HttpVersion,
-    pub headers: HashMap<String, String>,
-    pub body: String,
+    pub headers: HeaderMap,
+    pub trailers: HeaderMap,
+    pub body: Vec<u8>,
+    // A multipart/form-data body, which the parser writes to disk instead of `body`
+    pub spooled: Option<Arc<SpooledBody>>,
+    // Values set by the router and middleware, e.g. shared state
+    pub extensions: Extensions,
 }
 
 impl HttpRequest {
-    fn new() -> HttpRequest {
+    pub(crate) fn new() -> HttpRequest {
         HttpRequest {
+            id: NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed),
             method: HttpMethod::UNINITIALIZED,
             path: String::new(),
+            params: HashMap::new(),
             query: HashMap::new(),
+            query_pairs: Vec::new(),
             version: HttpVersion::UNINITIALIZED,
-            headers: HashMap::new(),
-            body: String::new(),
+            headers: HeaderMap::new(),
+            trailers: HeaderMap::new(),
+            body: Vec::new(),
+            spooled: None,
+            extensions: Extensions::new(),
         }
     }
-}
 
-impl From<String> for HttpRequest {
-    fn from(s: String) -> Self {
-        let mut lines = s.lines();
-        let mut request = HttpRequest::new();
+    // A path parameter captured by the router, e.g. `id` for `/users/:id`
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(|value| value.as_str())
+    }
 
-        if let Some(line) = lines.next() {
-            let mut parts = line.split_whitespace();
-            request.method = HttpMethod::new(parts.next().unwrap());
-            let path_with_query = parts.next().unwrap();
-            request.path = path_with_query.split('?').next().unwrap().to_string();
-            request.query = parts
-                .next()
-                .unwrap()
-                .split('&')
-                .fold(HashMap::new(), |mut acc, x| {
-                    let mut parts = x.split('=');
-                    let key = parts.next().unwrap().to_string();
-                    let value = parts.next().unwrap().to_string();
-                    acc.insert(key, value);
-                    acc
-                });
-            request.version = HttpVersion::new(parts.next().unwrap());
-        }
+    // Every value of a repeated query parameter, e.g. `tag` in `?tag=a&tag=b`
+    pub fn query_values(&self, name: &str) -> Vec<&str> {
+        self.query_pairs
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
 
-        for line in &mut lines {
-            if line.is_empty() {
-                break;
-            }
+    // The cookies sent in `Cookie` headers
+    pub fn cookies(&self) -> Cookies {
+        Cookies::parse(self.headers.get_all("Cookie"))
+    }
 
-            let mut parts = line.split(":");
-            let key = parts.next().unwrap().trim().to_string();
-            let value = parts.next().unwrap().trim().to_string();
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.cookies().get(name).map(|value| value.to_string())
+    }
 
-            request.headers.insert(key, value);
-        }
+    // Case-insensitive header lookup
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    // The body as text, if it is valid UTF-8
+    pub fn body_text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.body).ok()
+    }
 
-        request.body = lines.collect::<Vec<&str>>().join("\r\n");
-        request
+    // Whether the client is waiting for `100 Continue` before sending the body
+    pub fn expects_continue(&self) -> bool {
+        matches!(self.version, HttpVersion::HTTP11)
+            && self
+                .header("Expect")
+                .is_some_and(|value| value.eq_ignore_ascii_case("100-continue"))
     }
 }
 
 impl Debug for HttpRequest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HttpRequest")
+            .field("id", &self.id)
             .field("method", &self.method.to_string())
             .field("path", &self.path)
+            .field("params", &self.params)
             .field("query", &self.query)
             .field("version", &self.version.to_string())
             .field("headers", &self.headers)
-            .field("body", &self.body)
+            .field("trailers", &self.trailers)
+            .field("body", &String::from_utf8_lossy(&self.body))
+            .field("spooled", &self.spooled)
             .finish()
     }
 }
@@ -85,12 +113,12 @@ impl Display for HttpRequest {
         write!(
             f,
             "HttpRequest {{ method: {}, path: {}, query: {:#?} version: {}, headers: {:#?}, body: {} }}",
-            self.method.to_string(),
+            self.method,
             self.path,
             self.query,
-            self.version.to_string(),
+            self.version,
             self.headers,
-            self.body
+            String::from_utf8_lossy(&self.body)
         )
     }
 }
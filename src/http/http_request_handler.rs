@@ -1,11 +1,18 @@
-use super::{ContentType, HttpMethod, HttpRequest, HttpResponse, HttpVersion};
-use std::{env, fs};
+use super::{
+    range, urlencoded, Body, Conditional, ContentType, HttpMethod, HttpRequest, HttpResponse,
+    HttpVersion, Ranges, StatusCode,
+};
+use std::{env, fs::File, path::PathBuf};
 
 pub struct HttpRequestHandler {
     pub request: HttpRequest,
 }
 
 impl HttpRequestHandler {
+    // Static files are read-only
+    const ALLOWED_METHODS: [HttpMethod; 3] =
+        [HttpMethod::GET, HttpMethod::HEAD, HttpMethod::OPTIONS];
+
     pub fn new(request: HttpRequest) -> HttpRequestHandler {
         HttpRequestHandler { request }
     }
@@ -14,118 +21,92 @@ impl HttpRequestHandler {
         let method = &self.request.method;
 
         match method {
-            HttpMethod::GET => self.handle_get(),
-            HttpMethod::POST => panic!("Method not implemented!"),
-            HttpMethod::PUT => panic!("Method not implemented!"),
-            HttpMethod::DELETE => panic!("Method not implemented!"),
-            HttpMethod::HEAD => panic!("Method not implemented!"),
-            HttpMethod::CONNECT => panic!("Method not implemented!"),
-            HttpMethod::OPTIONS => panic!("Method not implemented!"),
-            HttpMethod::TRACE => panic!("Method not implemented!"),
-            HttpMethod::PATCH => panic!("Method not implemented!"),
-            HttpMethod::UNINITIALIZED => panic!("Method not initialized!"),
-        }
-    }
-
-    fn get_response_file_content(file: String) -> Option<ContentType> {
-        let mut file_type = None;
-
-        if let Some(dot) = file.find(".") {
-            file_type = Some(file[dot + 1..].to_string());
-        }
-
-        match file_type {
-            Some(file_type) => {
-                if file_type == "html" {
-                    return Some(ContentType::HTML);
-                } else if file_type == "css" {
-                    return Some(ContentType::CSS);
-                } else if file_type == "js" {
-                    return Some(ContentType::JS);
-                } else {
-                    return None;
-                }
-            }
-            None => None,
+            HttpMethod::GET | HttpMethod::HEAD => self.handle_get(),
+            HttpMethod::OPTIONS => HttpResponse::options(&Self::ALLOWED_METHODS),
+            _ => HttpResponse::method_not_allowed(&Self::ALLOWED_METHODS),
         }
     }
 
     pub fn serve_file(&self, file: &str) -> HttpResponse {
-        // Serves a file path without using the request
-        let mut response = HttpResponse::default();
         let cwd = env::current_dir().unwrap();
 
-        // Set the correct version
-        response.version = HttpVersion::HTTP20;
-
-        // Get the path to the file
-        let mut path = cwd.join(file);
-
-        // If the path is a directory, serve the index.html file
-        if path.is_dir() {
-            path = path.join("index.html");
-        }
-
-        // Get the content type of the file
-        if let Some(content_type) =
-            HttpRequestHandler::get_response_file_content(path.to_str().unwrap().to_string())
-        {
-            response
-                .headers
-                .insert("Content-Type".to_string(), content_type.to_string());
-        } else {
-            return HttpResponse::from(404);
-        }
-
-        // Read the file
-        if let Ok(contents) = fs::read_to_string(path) {
-            response.body = Some(contents);
-        } else {
-            return HttpResponse::from(404);
-        }
-
-        response
+        self.serve_path(cwd.join(file))
     }
 
     fn handle_get(&self) -> HttpResponse {
-        let mut response = HttpResponse::default();
-        let version = self.request.version.clone();
-
-        response.version = version;
-
         let cwd = env::current_dir().unwrap();
 
+        // Escapes are decoded before anything else so `%2e%2e` can't sneak past the check
+        let decoded = urlencoded::decode(&self.request.path);
+
         // Avoid path injection
-        if let Some(back) = self.request.path.find("..") {
-            if back > 0 {
-                return HttpResponse::from(403);
-            }
+        if decoded
+            .split(['/', '\\'])
+            .any(|segment| segment == ".." || segment.contains('\0'))
+        {
+            return HttpResponse::forbidden();
         }
 
         // Serve every directory inside of public/
-        let mut path = cwd
-            .join("public")
-            .join(&self.request.path.strip_prefix("/").unwrap());
+        let path = cwd.join("public").join(decoded.trim_start_matches('/'));
+
+        self.serve_path(path)
+    }
+
+    // Answers conditional requests from the file's metadata, HEAD gets the headers only
+    fn serve_path(&self, mut path: PathBuf) -> HttpResponse {
+        // If the path is a directory, serve the index.html file
         if path.is_dir() {
             path = path.join("index.html");
         }
 
-        if let Some(content_type) =
-            HttpRequestHandler::get_response_file_content(path.to_str().unwrap().to_string())
+        let content_type = ContentType::from_path(&path);
+
+        // Stream the file from disk instead of loading it into memory
+        let (file, metadata) = match File::open(&path).and_then(|file| Ok((file.metadata()?, file)))
         {
-            response
-                .headers
-                .insert("Content-Type".to_string(), content_type.to_string());
-        } else {
-            return HttpResponse::from(404);
+            Ok((metadata, file)) if metadata.is_file() => (file, metadata),
+            _ => return HttpResponse::not_found(),
+        };
+
+        let conditional = Conditional::from_metadata(&metadata);
+        if let Some(mut response) = conditional.check(&self.request) {
+            // Describes the cached 200 so middleware such as compression can
+            // give the 304 the same headers
+            if response.status == StatusCode::NOT_MODIFIED {
+                response.headers.set_content_type(content_type);
+                response.headers.set_content_length(metadata.len());
+            }
+            return response;
         }
 
-        if let Ok(contents) = fs::read_to_string(path) {
-            response.body = Some(contents);
-        } else {
-            return HttpResponse::from(404);
-        }
+        let length = metadata.len();
+        let mut response = match Ranges::from_request(&self.request, length, &conditional) {
+            Ranges::Full => {
+                let mut response = HttpResponse {
+                    version: HttpVersion::HTTP11,
+                    status: StatusCode::OK,
+                    ..Default::default()
+                };
+                response.headers.set_content_type(content_type);
+                response.headers.set_content_length(length);
+                response.headers.insert("Accept-Ranges", "bytes");
+                response.body = Body::from_reader(file, Some(length));
+                response
+            }
+            Ranges::Partial(ranges) => {
+                match range::partial_response(file, length, &content_type, &ranges) {
+                    Ok(response) => response,
+                    Err(_) => return HttpResponse::internal_server_error(),
+                }
+            }
+            Ranges::Unsatisfiable => range::not_satisfiable(length),
+        };
+        conditional.apply(&mut response);
 
-        response
+        match self.request.method {
+            HttpMethod::HEAD => response.into_head(),
+            _ => response,
+        }
     }
 }
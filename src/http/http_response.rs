@@ -1,110 +1,404 @@
 use crate::Template;
 
-use super::HttpVersion;
-use std::{collections::HashMap, env, fs};
+use super::{
+    date, Body, Cookie, CookieError, HeaderMap, HttpMethod, HttpVersion, IntoResponse, StatusCode,
+};
+use std::{
+    io::{self, Write},
+    time::SystemTime,
+};
 
 pub struct HttpResponse {
     pub version: HttpVersion,
-    pub status_code: String,
-    pub status_text: String,
-    pub headers: HashMap<String, String>,
-    pub body: Option<String>,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Body,
+    // Explains an error to the error page rendered for it, never sent as is
+    pub message: Option<String>,
 }
 
 impl HttpResponse {
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let mut response = String::new();
-
-        response.push_str(&format!(
-            "{} {} {}\r\n",
-            self.version.to_string(),
-            self.status_code,
-            self.status_text
-        ));
-
-        if self.headers.len() > 0 {
-            for (key, value) in self.headers.iter() {
-                response.push_str(&format!("{}: {}\r\n", key, value));
+    // A bare response with no headers and no body
+    pub fn new(status: StatusCode) -> HttpResponse {
+        HttpResponse {
+            version: HttpVersion::HTTP11,
+            status,
+            ..Default::default()
+        }
+    }
+
+    pub fn created(location: &str) -> HttpResponse {
+        let mut response = HttpResponse::new(StatusCode::CREATED);
+        response.headers.insert("Location", location);
+        response
+    }
+
+    pub fn no_content() -> HttpResponse {
+        HttpResponse::new(StatusCode::NO_CONTENT)
+    }
+
+    // Redirects to `location` with any 3xx status, see the helpers below for the common ones
+    pub fn redirect(status: StatusCode, location: &str) -> HttpResponse {
+        assert!(status.is_redirect(), "{} is not a redirect status", status);
+
+        let mut response = HttpResponse::new(status);
+        response.headers.insert("Location", location);
+        response
+    }
+
+    pub fn moved_permanently(location: &str) -> HttpResponse {
+        HttpResponse::redirect(StatusCode::MOVED_PERMANENTLY, location)
+    }
+
+    pub fn found(location: &str) -> HttpResponse {
+        HttpResponse::redirect(StatusCode::FOUND, location)
+    }
+
+    pub fn see_other(location: &str) -> HttpResponse {
+        HttpResponse::redirect(StatusCode::SEE_OTHER, location)
+    }
+
+    pub fn temporary_redirect(location: &str) -> HttpResponse {
+        HttpResponse::redirect(StatusCode::TEMPORARY_REDIRECT, location)
+    }
+
+    pub fn permanent_redirect(location: &str) -> HttpResponse {
+        HttpResponse::redirect(StatusCode::PERMANENT_REDIRECT, location)
+    }
+
+    pub fn bad_request() -> HttpResponse {
+        HttpResponse::from(StatusCode::BAD_REQUEST)
+    }
+
+    // `challenge` is the WWW-Authenticate value, e.g. `Basic realm="admin"`
+    pub fn unauthorized(challenge: &str) -> HttpResponse {
+        let mut response = HttpResponse::from(StatusCode::UNAUTHORIZED);
+        response.headers.insert("WWW-Authenticate", challenge);
+        response
+    }
+
+    pub fn forbidden() -> HttpResponse {
+        HttpResponse::from(StatusCode::FORBIDDEN)
+    }
+
+    pub fn not_found() -> HttpResponse {
+        HttpResponse::from(StatusCode::NOT_FOUND)
+    }
+
+    pub fn payload_too_large() -> HttpResponse {
+        HttpResponse::from(StatusCode::CONTENT_TOO_LARGE)
+    }
+
+    pub fn internal_server_error() -> HttpResponse {
+        HttpResponse::from(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    pub fn service_unavailable() -> HttpResponse {
+        HttpResponse::from(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    // Attaches a message for the router's error page to show
+    pub fn with_message(mut self, message: &str) -> HttpResponse {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    // Adds a `Set-Cookie` header, one per cookie. Panics on a cookie that
+    // doesn't pass `Cookie::validate`, see `try_set_cookie`.
+    pub fn set_cookie(&mut self, cookie: Cookie) {
+        if let Err(e) = self.try_set_cookie(cookie) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_set_cookie(&mut self, cookie: Cookie) -> Result<(), CookieError> {
+        cookie.validate()?;
+        self.headers.append("Set-Cookie", &cookie.to_string());
+        Ok(())
+    }
+
+    pub fn with_cookie(mut self, cookie: Cookie) -> HttpResponse {
+        self.set_cookie(cookie);
+        self
+    }
+
+    // Tells the client to delete a cookie set on path `/`
+    pub fn remove_cookie(&mut self, name: &str) {
+        self.set_cookie(Cookie::removal(name).path("/"));
+    }
+
+    // Case-insensitive header lookup
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    // Answers a HEAD request: same headers as the GET response, no body
+    pub fn into_head(mut self) -> HttpResponse {
+        if !self.status.allows_body() {
+            return self;
+        }
+
+        let framed = self.header("Content-Length").is_some()
+            || self.headers.has_token("Transfer-Encoding", "chunked");
+        if !framed {
+            match self.body.len() {
+                Some(length) => self.headers.set_content_length(length),
+                None => self.headers.insert("Transfer-Encoding", "chunked"),
             }
-        } else {
-            response.push_str("Content-Type: text/html\r\n");
         }
+        if self.header("Content-Type").is_none() {
+            self.headers.insert("Content-Type", DEFAULT_CONTENT_TYPE);
+        }
+
+        self.body = Body::Empty;
+        self
+    }
+
+    // An automatic answer to OPTIONS listing the methods a resource supports
+    pub fn options(allowed: &[HttpMethod]) -> HttpResponse {
+        let mut response = HttpResponse::no_content();
+        response.headers.insert("Allow", &allow_header(allowed));
+        response
+    }
+
+    pub fn method_not_allowed(allowed: &[HttpMethod]) -> HttpResponse {
+        let mut response = HttpResponse::from(StatusCode::METHOD_NOT_ALLOWED);
+        response.headers.insert("Allow", &allow_header(allowed));
+        response
+    }
 
-        response.push_str("\r\n");
+    // Serializes the response in memory with the default options. Streaming
+    // bodies can only be read once, so for those this is None and `write_to`
+    // has to send the response.
+    pub fn as_bytes(&self) -> Option<Vec<u8>> {
+        let body = self.body.as_bytes()?;
+        let mut headers = self.headers.clone();
+        let framing = frame(
+            &mut headers,
+            self.status,
+            &self.body,
+            &WriteOptions::default(),
+        );
 
-        if let Some(body) = &self.body {
-            response.push_str(body);
+        let mut response = Vec::new();
+        // Writing into a Vec can't fail
+        let _ = write_head(&mut response, &self.version, self.status, &headers);
+        if framing.body {
+            let _ = Body::from(body.to_vec()).write_to(&mut response, framing.chunked);
         }
 
-        response.as_bytes().to_vec()
+        Some(response)
+    }
+
+    // Writes the response with the default options, see `write_with`
+    pub fn write_to<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        self.write_with(writer, &WriteOptions::default())
+    }
+
+    // Writes the status line and headers, then streams the body. Framing,
+    // `Date` and `Server` headers are added unless the handler set its own.
+    pub fn write_with<W: Write>(
+        mut self,
+        writer: &mut W,
+        options: &WriteOptions,
+    ) -> io::Result<()> {
+        let framing = frame(&mut self.headers, self.status, &self.body, options);
+
+        write_head(writer, &self.version, self.status, &self.headers)?;
+        if framing.body {
+            self.body.write_to(writer, framing.chunked)?;
+        }
+
+        Ok(())
     }
 }
 
-impl Default for HttpResponse {
-    fn default() -> HttpResponse {
-        HttpResponse {
-            version: HttpVersion::UNINITIALIZED,
-            status_code: String::new(),
-            status_text: String::new(),
-            headers: HashMap::new(),
-            body: None,
+pub const SERVER: &str = concat!("rust_webserver/", env!("CARGO_PKG_VERSION"));
+
+const DEFAULT_CONTENT_TYPE: &str = "text/html";
+
+// How a response is put on the wire, the connection fills this in from the request
+pub struct WriteOptions {
+    // Answering a HEAD request, the headers are those of a GET response
+    pub head: bool,
+    // Whether the client understands chunked transfer coding (not HTTP/1.0)
+    pub chunked: bool,
+    // The `Server` header, None leaves it out
+    pub server: Option<String>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            head: false,
+            chunked: true,
+            server: Some(SERVER.to_string()),
         }
     }
 }
 
-impl From<u16> for HttpResponse {
-    fn from(status_code: u16) -> Self {
-        let mut response = HttpResponse::default();
-        response.version = HttpVersion::HTTP11;
-        response.status_code = status_code.to_string();
-        response.status_text = match status_code {
-            403 => "Forbidden",
-            404 => "Not Found",
-            _ => "Unknown",
+struct Framing {
+    // Whether a body is sent at all
+    body: bool,
+    chunked: bool,
+}
+
+// Completes the headers for sending: the body is framed by Content-Length when its
+// size is known, chunked otherwise, or by closing the connection for HTTP/1.0
+fn frame(
+    headers: &mut HeaderMap,
+    status: StatusCode,
+    body: &Body,
+    options: &WriteOptions,
+) -> Framing {
+    if !headers.contains("Date") {
+        headers.insert("Date", &date::format(SystemTime::now()));
+    }
+    if let Some(server) = &options.server {
+        if !headers.contains("Server") {
+            headers.insert("Server", server);
         }
-        .to_string();
+    }
 
-        let cwd = env::current_dir().unwrap();
-        let path = cwd.join("private").join(format!("{}.html", status_code));
+    // These never have a body, a 304 keeps the Content-Length of the full response
+    if !status.allows_body() {
+        if status != StatusCode::NOT_MODIFIED {
+            headers.remove("Content-Length");
+        }
+        headers.remove("Transfer-Encoding");
+        return Framing {
+            body: false,
+            chunked: false,
+        };
+    }
 
-        if let Ok(body) = fs::read_to_string(path) {
-            response.body = Some(body);
+    let mut chunked = headers.has_token("Transfer-Encoding", "chunked");
+    if !chunked && !headers.contains("Content-Length") {
+        match body.len() {
+            Some(length) => headers.set_content_length(length),
+            None if options.chunked => {
+                headers.insert("Transfer-Encoding", "chunked");
+                chunked = true;
+            }
+            None => {}
         }
+    }
 
-        response
+    if chunked {
+        if options.chunked {
+            // Content-Length must not be sent alongside Transfer-Encoding
+            headers.remove("Content-Length");
+        } else {
+            headers.remove("Transfer-Encoding");
+            chunked = false;
+        }
+    }
+
+    if !headers.contains("Content-Type") && body.len() != Some(0) {
+        headers.insert("Content-Type", DEFAULT_CONTENT_TYPE);
+    }
+
+    Framing {
+        body: !options.head,
+        chunked,
     }
 }
 
-impl From<String> for HttpResponse {
-    fn from(text: String) -> Self {
-        let mut response = HttpResponse::default();
-        response.version = HttpVersion::HTTP11;
-        response.status_code = "200".to_string();
-        response.status_text = "OK".to_string();
+fn write_head<W: Write>(
+    writer: &mut W,
+    version: &HttpVersion,
+    status: StatusCode,
+    headers: &HeaderMap,
+) -> io::Result<()> {
+    write!(writer, "{} {}\r\n", version, status)?;
+    for (key, value) in headers.iter() {
+        write!(writer, "{}: {}\r\n", key, value)?;
+    }
+    writer.write_all(b"\r\n")
+}
 
-        let cwd = env::current_dir().unwrap();
-        let path = cwd.join("templates").join("text.html");
+fn allow_header(allowed: &[HttpMethod]) -> String {
+    allowed
+        .iter()
+        .map(|method| method.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
 
-        if let Ok(body) = fs::read_to_string(path) {
-            response.body = Some(body.replace("{{body}}", &text));
+impl Default for HttpResponse {
+    fn default() -> HttpResponse {
+        HttpResponse {
+            version: HttpVersion::UNINITIALIZED,
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Body::Empty,
+            message: None,
         }
+    }
+}
 
-        response
+// Error statuses get their page from the router's error handlers
+impl From<StatusCode> for HttpResponse {
+    fn from(status: StatusCode) -> Self {
+        HttpResponse::new(status)
+    }
+}
+
+impl From<u16> for HttpResponse {
+    fn from(status_code: u16) -> Self {
+        let status = StatusCode::from_u16(status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        HttpResponse::from(status)
+    }
+}
+
+// Plain text, see `IntoResponse`
+impl From<String> for HttpResponse {
+    fn from(text: String) -> Self {
+        text.into_response()
     }
 }
 
 impl From<Template> for HttpResponse {
-    fn from(mut template: Template) -> Self {
-        let mut response = HttpResponse::default();
-        response.version = HttpVersion::HTTP20;
-        response.status_code = "200".to_string();
-        response.status_text = "OK".to_string();
-
-        if let Some(body) = &template.rendered {
-            response.body = Some(body.to_string());
-        } else {
-            template.render(HashMap::new());
-        }
-        response
+    fn from(template: Template) -> Self {
+        template.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(response: &HttpResponse) -> String {
+        String::from_utf8(response.as_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn in_memory_bodies_are_framed() {
+        let response = HttpResponse::from("hello".to_string());
+        let text = text(&response);
+
+        assert!(text.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(text.contains("\r\nContent-Length: 5\r\n"));
+        assert!(text.contains("\r\nDate: "));
+        assert!(text.ends_with("\r\n\r\nhello"));
+    }
+
+    #[test]
+    fn bodiless_statuses_leave_the_body_out() {
+        let mut response = HttpResponse::new(StatusCode::NO_CONTENT);
+        response.body = Body::from("ignored".to_string());
+
+        assert!(text(&response).ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn streaming_bodies_are_not_serialized() {
+        let mut response = HttpResponse::new(StatusCode::OK);
+        response.body = Body::from_reader(io::Cursor::new(b"stream".to_vec()), Some(6));
+        assert!(response.as_bytes().is_none());
+
+        let mut out = Vec::new();
+        response.write_to(&mut out).unwrap();
+        assert!(out.ends_with(b"\r\n\r\nstream"));
     }
 }
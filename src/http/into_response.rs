@@ -0,0 +1,153 @@
+use super::{Body, ContentType, HttpError, HttpResponse, StatusCode};
+use crate::{json, JsonConfig, Template};
+
+// Anything a handler can return. Text and bytes become a 200 with a matching
+// Content-Type, a status on its own becomes an empty response (which gets the
+// router's error page for error statuses).
+pub trait IntoResponse {
+    fn into_response(self) -> HttpResponse;
+
+    // Route handlers convert with the router's `JsonConfig`, which only changes
+    // how `Json` values are written
+    fn into_response_with(self, config: &JsonConfig) -> HttpResponse
+    where
+        Self: Sized,
+    {
+        let _ = config;
+        self.into_response()
+    }
+}
+
+impl IntoResponse for HttpResponse {
+    fn into_response(self) -> HttpResponse {
+        self
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> HttpResponse {
+        HttpError::into_response(self)
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> HttpResponse {
+        HttpResponse::new(self)
+    }
+}
+
+impl IntoResponse for &str {
+    fn into_response(self) -> HttpResponse {
+        self.to_string().into_response()
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> HttpResponse {
+        with_body(ContentType::TEXT, Body::from(self))
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> HttpResponse {
+        with_body(ContentType::BINARY, Body::from(self))
+    }
+}
+
+impl IntoResponse for json::Value {
+    fn into_response(self) -> HttpResponse {
+        with_body(ContentType::JSON, Body::from(self.to_compact()))
+    }
+}
+
+// Renders the template with the variables it was given if that hasn't happened yet
+impl IntoResponse for Template {
+    fn into_response(mut self) -> HttpResponse {
+        if self.rendered.is_none() {
+            let env = self.env.clone();
+            self.render(env);
+        }
+
+        match self.rendered {
+            Some(rendered) => with_body(ContentType::HTML, Body::from(rendered)),
+            None => HttpResponse::internal_server_error(),
+        }
+    }
+}
+
+// `(StatusCode::CREATED, "done")`
+impl<B: IntoResponse> IntoResponse for (StatusCode, B) {
+    fn into_response(self) -> HttpResponse {
+        self.into_response_with(&JsonConfig::default())
+    }
+
+    fn into_response_with(self, config: &JsonConfig) -> HttpResponse {
+        let (status, body) = self;
+
+        let mut response = body.into_response_with(config);
+        response.status = status;
+        response
+    }
+}
+
+// `(StatusCode::OK, [("Cache-Control", "no-store")], "done")`
+impl<H, K, V, B> IntoResponse for (StatusCode, H, B)
+where
+    H: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+    B: IntoResponse,
+{
+    fn into_response(self) -> HttpResponse {
+        self.into_response_with(&JsonConfig::default())
+    }
+
+    fn into_response_with(self, config: &JsonConfig) -> HttpResponse {
+        let (status, headers, body) = self;
+
+        let mut response = (status, body).into_response_with(config);
+        for (key, value) in headers {
+            response.headers.insert(&key.into(), &value.into());
+        }
+        response
+    }
+}
+
+// Lets handlers use `?` on anything that converts into an `HttpError`
+impl<T, E> IntoResponse for Result<T, E>
+where
+    T: IntoResponse,
+    E: Into<HttpError>,
+{
+    fn into_response(self) -> HttpResponse {
+        self.into_response_with(&JsonConfig::default())
+    }
+
+    fn into_response_with(self, config: &JsonConfig) -> HttpResponse {
+        match self {
+            Ok(response) => response.into_response_with(config),
+            Err(e) => e.into().into_response(),
+        }
+    }
+}
+
+// Nothing to return is a 404, e.g. for a lookup by id
+impl<T: IntoResponse> IntoResponse for Option<T> {
+    fn into_response(self) -> HttpResponse {
+        self.into_response_with(&JsonConfig::default())
+    }
+
+    fn into_response_with(self, config: &JsonConfig) -> HttpResponse {
+        match self {
+            Some(response) => response.into_response_with(config),
+            None => HttpResponse::not_found(),
+        }
+    }
+}
+
+fn with_body(content_type: ContentType, body: Body) -> HttpResponse {
+    let mut response = HttpResponse::new(StatusCode::OK);
+    response.headers.set_content_type(content_type);
+    response.body = body;
+    response
+}
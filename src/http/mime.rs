@@ -0,0 +1,117 @@
+use super::ContentType;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{OnceLock, RwLock},
+};
+
+// Built-in extension to MIME type table, keyed on the lowercase final extension
+static MIME_TYPES: &[(&str, &str)] = &[
+    // Text
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("txt", "text/plain"),
+    ("csv", "text/csv"),
+    ("md", "text/markdown"),
+    ("ics", "text/calendar"),
+    // Data
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("webmanifest", "application/manifest+json"),
+    ("jsonld", "application/ld+json"),
+    ("xml", "application/xml"),
+    ("rss", "application/rss+xml"),
+    ("atom", "application/atom+xml"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    // Images
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    // Fonts
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("eot", "application/vnd.ms-fontobject"),
+    // Audio and video
+    ("mp4", "video/mp4"),
+    ("m4v", "video/mp4"),
+    ("webm", "video/webm"),
+    ("ogv", "video/ogg"),
+    ("mov", "video/quicktime"),
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("ogg", "audio/ogg"),
+    ("oga", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("flac", "audio/flac"),
+    // Archives
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("7z", "application/x-7z-compressed"),
+];
+
+// Mappings registered at startup, these take precedence over the built-in table
+fn registry() -> &'static RwLock<HashMap<String, String>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+static FALLBACK: RwLock<Option<ContentType>> = RwLock::new(None);
+
+// Registers (or overrides) the MIME type served for an extension, e.g. `register("md", "text/plain")`
+pub fn register(extension: &str, mime: &str) {
+    let extension = extension.trim_start_matches('.').to_lowercase();
+    registry()
+        .write()
+        .unwrap()
+        .insert(extension, mime.to_string());
+}
+
+// Sets the type used for files whose extension is unknown
+pub fn set_fallback(content_type: ContentType) {
+    *FALLBACK.write().unwrap() = Some(content_type);
+}
+
+pub fn fallback() -> ContentType {
+    FALLBACK
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or(ContentType::BINARY)
+}
+
+pub fn from_extension(extension: &str) -> Option<ContentType> {
+    let extension = extension.trim_start_matches('.').to_lowercase();
+
+    if let Some(mime) = registry().read().unwrap().get(&extension) {
+        return Some(ContentType::new(mime));
+    }
+
+    MIME_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime)| ContentType::new(mime))
+}
+
+// Only the final extension counts, so `jquery.min.js` is JavaScript and
+// dots in directory names are ignored
+pub fn from_path(path: &Path) -> ContentType {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(from_extension)
+        .unwrap_or_else(fallback)
+}
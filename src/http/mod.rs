@@ -1,12 +1,42 @@
+pub use body::Body;
+pub use conditional::{Conditional, ETag};
+pub use connection::{Connection, KeepAlive};
+pub use cookie::{Cookie, CookieError, Cookies, Key, SameSite};
+pub use error::HttpError;
+pub use extensions::Extensions;
+pub use header_map::{HeaderError, HeaderMap};
 pub use http_request::HttpRequest;
 pub use http_request_handler::HttpRequestHandler;
-pub use http_response::HttpResponse;
+pub use http_response::{HttpResponse, WriteOptions};
+pub use into_response::IntoResponse;
+pub use parser::{ParseError, ParserLimits, RequestParser};
+pub use range::{ByteRange, Ranges};
+pub use spooled::SpooledBody;
+pub use status::StatusCode;
 
+pub mod body;
+pub mod conditional;
+pub mod connection;
+pub mod cookie;
+pub mod date;
+pub mod error;
+pub mod extensions;
+pub mod header_map;
 pub mod http_request;
 pub mod http_request_handler;
 pub mod http_response;
+pub mod into_response;
+pub mod mime;
+pub mod parser;
+pub mod range;
+pub mod spooled;
+pub mod status;
+pub mod urlencoded;
+
+use std::fmt::{self, Display, Formatter};
 
 // Define important enums
+#[derive(PartialEq, Eq, Hash)]
 pub enum HttpMethod {
     GET,
     POST,
@@ -21,8 +51,9 @@ pub enum HttpMethod {
 }
 
 impl HttpMethod {
+    // Method names are case-sensitive, `get` is not GET
     pub fn new(s: &str) -> HttpMethod {
-        match s.to_uppercase().as_str() {
+        match s {
             "GET" => HttpMethod::GET,
             "POST" => HttpMethod::POST,
             "PUT" => HttpMethod::PUT,
@@ -37,20 +68,21 @@ impl HttpMethod {
     }
 }
 
-impl ToString for HttpMethod {
-    fn to_string(&self) -> String {
-        match self {
-            HttpMethod::GET => "GET".to_string(),
-            HttpMethod::POST => "POST".to_string(),
-            HttpMethod::PUT => "PUT".to_string(),
-            HttpMethod::DELETE => "DELETE".to_string(),
-            HttpMethod::HEAD => "HEAD".to_string(),
-            HttpMethod::CONNECT => "CONNECT".to_string(),
-            HttpMethod::OPTIONS => "OPTIONS".to_string(),
-            HttpMethod::TRACE => "TRACE".to_string(),
-            HttpMethod::PATCH => "PATCH".to_string(),
-            HttpMethod::UNINITIALIZED => "UNINITIALIZED".to_string(),
-        }
+impl Display for HttpMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::PUT => "PUT",
+            HttpMethod::DELETE => "DELETE",
+            HttpMethod::HEAD => "HEAD",
+            HttpMethod::CONNECT => "CONNECT",
+            HttpMethod::OPTIONS => "OPTIONS",
+            HttpMethod::TRACE => "TRACE",
+            HttpMethod::PATCH => "PATCH",
+            HttpMethod::UNINITIALIZED => "UNINITIALIZED",
+        };
+        write!(f, "{}", s)
     }
 }
 
@@ -79,8 +111,9 @@ pub enum HttpVersion {
 }
 
 impl HttpVersion {
+    // The protocol name is case-sensitive too
     pub fn new(s: &str) -> HttpVersion {
-        match s.to_uppercase().as_str() {
+        match s {
             "HTTP/1.0" => HttpVersion::HTTP10,
             "HTTP/1.1" => HttpVersion::HTTP11,
             "HTTP/2.0" => HttpVersion::HTTP20,
@@ -100,31 +133,146 @@ impl Clone for HttpVersion {
     }
 }
 
-impl ToString for HttpVersion {
-    fn to_string(&self) -> String {
-        match self {
-            HttpVersion::HTTP10 => "HTTP/1.0".to_string(),
-            HttpVersion::HTTP11 => "HTTP/1.1".to_string(),
-            HttpVersion::HTTP20 => "HTTP/2.0".to_string(),
-            HttpVersion::UNINITIALIZED => "UNINITIALIZED".to_string(),
-        }
+impl Display for HttpVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            HttpVersion::HTTP10 => "HTTP/1.0",
+            HttpVersion::HTTP11 => "HTTP/1.1",
+            HttpVersion::HTTP20 => "HTTP/2.0",
+            HttpVersion::UNINITIALIZED => "UNINITIALIZED",
+        };
+        write!(f, "{}", s)
     }
 }
 
+#[derive(Clone, PartialEq, Eq)]
 pub enum ContentType {
     HTML,
     CSS,
     JS,
+    JSON,
+    XML,
+    TEXT,
+    CSV,
+    SVG,
+    PNG,
+    JPEG,
+    GIF,
+    WEBP,
+    AVIF,
+    ICO,
+    WOFF,
+    WOFF2,
+    TTF,
+    OTF,
+    WASM,
+    PDF,
+    ZIP,
+    GZIP,
+    MP4,
+    WEBM,
+    MP3,
+    OGG,
+    WAV,
+    BINARY,
+    Other(String),
     UNINITIALIZED,
 }
 
-impl ToString for ContentType {
-    fn to_string(&self) -> String {
+impl ContentType {
+    pub fn new(s: &str) -> ContentType {
+        match s.trim().to_lowercase().as_str() {
+            "text/html" => ContentType::HTML,
+            "text/css" => ContentType::CSS,
+            "text/javascript" | "application/javascript" => ContentType::JS,
+            "application/json" => ContentType::JSON,
+            "application/xml" | "text/xml" => ContentType::XML,
+            "text/plain" => ContentType::TEXT,
+            "text/csv" => ContentType::CSV,
+            "image/svg+xml" => ContentType::SVG,
+            "image/png" => ContentType::PNG,
+            "image/jpeg" => ContentType::JPEG,
+            "image/gif" => ContentType::GIF,
+            "image/webp" => ContentType::WEBP,
+            "image/avif" => ContentType::AVIF,
+            "image/x-icon" | "image/vnd.microsoft.icon" => ContentType::ICO,
+            "font/woff" => ContentType::WOFF,
+            "font/woff2" => ContentType::WOFF2,
+            "font/ttf" => ContentType::TTF,
+            "font/otf" => ContentType::OTF,
+            "application/wasm" => ContentType::WASM,
+            "application/pdf" => ContentType::PDF,
+            "application/zip" => ContentType::ZIP,
+            "application/gzip" => ContentType::GZIP,
+            "video/mp4" => ContentType::MP4,
+            "video/webm" => ContentType::WEBM,
+            "audio/mpeg" => ContentType::MP3,
+            "audio/ogg" => ContentType::OGG,
+            "audio/wav" => ContentType::WAV,
+            "application/octet-stream" => ContentType::BINARY,
+            _ => ContentType::Other(s.trim().to_string()),
+        }
+    }
+
+    // Looks up the type for a file by its final extension, see `mime::from_extension`
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> ContentType {
+        mime::from_path(path.as_ref())
+    }
+
+    // The bare MIME type, without any parameters
+    pub fn mime(&self) -> &str {
+        match self {
+            ContentType::HTML => "text/html",
+            ContentType::CSS => "text/css",
+            ContentType::JS => "text/javascript",
+            ContentType::JSON => "application/json",
+            ContentType::XML => "application/xml",
+            ContentType::TEXT => "text/plain",
+            ContentType::CSV => "text/csv",
+            ContentType::SVG => "image/svg+xml",
+            ContentType::PNG => "image/png",
+            ContentType::JPEG => "image/jpeg",
+            ContentType::GIF => "image/gif",
+            ContentType::WEBP => "image/webp",
+            ContentType::AVIF => "image/avif",
+            ContentType::ICO => "image/x-icon",
+            ContentType::WOFF => "font/woff",
+            ContentType::WOFF2 => "font/woff2",
+            ContentType::TTF => "font/ttf",
+            ContentType::OTF => "font/otf",
+            ContentType::WASM => "application/wasm",
+            ContentType::PDF => "application/pdf",
+            ContentType::ZIP => "application/zip",
+            ContentType::GZIP => "application/gzip",
+            ContentType::MP4 => "video/mp4",
+            ContentType::WEBM => "video/webm",
+            ContentType::MP3 => "audio/mpeg",
+            ContentType::OGG => "audio/ogg",
+            ContentType::WAV => "audio/wav",
+            ContentType::BINARY => "application/octet-stream",
+            ContentType::Other(mime) => mime.split(';').next().unwrap_or("").trim(),
+            ContentType::UNINITIALIZED => "UNINITIALIZED",
+        }
+    }
+
+    // Whether the type is textual and should carry a charset parameter
+    pub fn is_text(&self) -> bool {
+        let mime = self.mime();
+
+        mime.starts_with("text/")
+            || mime.ends_with("+xml")
+            || mime.ends_with("+json")
+            || matches!(self, ContentType::JS | ContentType::JSON | ContentType::XML)
+    }
+}
+
+impl Display for ContentType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            ContentType::HTML => "text/html".to_string(),
-            ContentType::CSS => "text/css".to_string(),
-            ContentType::JS => "text/javascript".to_string(),
-            ContentType::UNINITIALIZED => "UNINITIALIZED".to_string(),
+            // Custom types with explicit parameters are written as registered
+            ContentType::Other(mime) if mime.contains(';') => write!(f, "{}", mime),
+            _ if self.is_text() => write!(f, "{}; charset=utf-8", self.mime()),
+            _ => write!(f, "{}", self.mime()),
         }
     }
 }
@@ -0,0 +1,847 @@
+use super::{urlencoded, HeaderMap, HttpMethod, HttpRequest, HttpVersion, SpooledBody, StatusCode};
+use std::{
+    fmt::{self, Debug, Display},
+    io::{self, BufWriter, Read, Write},
+    sync::Arc,
+};
+
+const READ_CHUNK_SIZE: usize = 4096;
+
+pub struct ParserLimits {
+    pub max_request_line: usize,
+    pub max_header_size: usize,
+    pub max_headers: usize,
+    pub max_body_size: usize,
+    // Largest multipart/form-data body. These are written to a temporary file
+    // instead of memory, see `HttpRequest::spooled`. This is checked before
+    // routing, so it defaults to `FormLimits::max_total_size` and any URL can
+    // only make the server store that much; raise both for bigger uploads.
+    pub max_spooled_size: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> ParserLimits {
+        ParserLimits {
+            max_request_line: 8 * 1024,
+            max_header_size: 16 * 1024,
+            max_headers: 100,
+            max_body_size: 10 * 1024 * 1024,
+            max_spooled_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+pub enum ParseError {
+    Io(io::Error),
+    UnexpectedEof,
+    RequestLineTooLong,
+    HeadersTooLarge,
+    TooManyHeaders,
+    InvalidRequestLine,
+    InvalidMethod,
+    InvalidVersion,
+    InvalidHeader,
+    InvalidContentLength,
+    InvalidChunk,
+    UnsupportedTransferEncoding,
+    PayloadTooLarge,
+    ExpectationFailed,
+    // Writing a spooled body to disk failed, the request itself may be fine
+    Spool(io::Error),
+}
+
+impl ParseError {
+    // The status code a client should receive for this error
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ParseError::RequestLineTooLong => StatusCode::URI_TOO_LONG,
+            ParseError::PayloadTooLarge => StatusCode::CONTENT_TOO_LARGE,
+            ParseError::ExpectationFailed => StatusCode::EXPECTATION_FAILED,
+            ParseError::Spool(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ParseError::HeadersTooLarge | ParseError::TooManyHeaders => {
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+            }
+            ParseError::InvalidMethod | ParseError::UnsupportedTransferEncoding => {
+                StatusCode::NOT_IMPLEMENTED
+            }
+            ParseError::InvalidVersion => StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "I/O error while reading request: {}", e),
+            ParseError::UnexpectedEof => write!(f, "connection closed mid-request"),
+            ParseError::RequestLineTooLong => write!(f, "request line too long"),
+            ParseError::HeadersTooLarge => write!(f, "request headers too large"),
+            ParseError::TooManyHeaders => write!(f, "too many request headers"),
+            ParseError::InvalidRequestLine => write!(f, "malformed request line"),
+            ParseError::InvalidMethod => write!(f, "unknown request method"),
+            ParseError::InvalidVersion => write!(f, "unsupported HTTP version"),
+            ParseError::InvalidHeader => write!(f, "malformed header line"),
+            ParseError::InvalidContentLength => write!(f, "invalid Content-Length"),
+            ParseError::InvalidChunk => write!(f, "malformed chunked body"),
+            ParseError::UnsupportedTransferEncoding => {
+                write!(f, "unsupported Transfer-Encoding")
+            }
+            ParseError::PayloadTooLarge => write!(f, "request body too large"),
+            ParseError::ExpectationFailed => write!(f, "unsupported expectation"),
+            ParseError::Spool(e) => write!(f, "failed to spool request body: {}", e),
+        }
+    }
+}
+
+impl Debug for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ParseError({})", self)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+enum BodyLength {
+    Fixed(usize),
+    Chunked,
+}
+
+pub struct RequestParser<R: Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    limits: ParserLimits,
+}
+
+impl<R: Read> RequestParser<R> {
+    pub fn new(reader: R) -> RequestParser<R> {
+        RequestParser::with_limits(reader, ParserLimits::default())
+    }
+
+    pub fn with_limits(reader: R, limits: ParserLimits) -> RequestParser<R> {
+        RequestParser {
+            reader,
+            buffer: Vec::with_capacity(READ_CHUNK_SIZE),
+            limits,
+        }
+    }
+
+    // Reads the next request, including its body, from the underlying reader.
+    // Returns Ok(None) if the peer closed the connection before sending anything.
+    pub fn next_request(&mut self) -> Result<Option<HttpRequest>, ParseError> {
+        match self.read_head()? {
+            Some(mut request) => {
+                self.read_body(&mut request)?;
+                Ok(Some(request))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Reads the request line and headers only, leaving the body on the wire.
+    // This lets callers answer `Expect: 100-continue` before calling `read_body`.
+    pub fn read_head(&mut self) -> Result<Option<HttpRequest>, ParseError> {
+        // Skip empty lines preceding the request line (RFC 7230 section 3.5)
+        let line = loop {
+            match self.read_line(self.limits.max_request_line)? {
+                Some(line) if line.is_empty() => continue,
+                Some(line) => break line,
+                None => return Ok(None),
+            }
+        };
+
+        let mut request = HttpRequest::new();
+        Self::parse_request_line(&line, &mut request)?;
+        self.read_fields(&mut request.headers)?;
+
+        Ok(Some(request))
+    }
+
+    // Validates the body framing of a request and its announced length without
+    // reading anything. Chunked bodies can only be checked while reading them.
+    pub fn check_body_length(&self, request: &HttpRequest) -> Result<(), ParseError> {
+        match Self::body_length(request)? {
+            BodyLength::Fixed(length) if length > self.body_limit(request) => {
+                Err(ParseError::PayloadTooLarge)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Reads the body announced by the request headers into `request.body`, or
+    // into `request.spooled` for form uploads
+    pub fn read_body(&mut self, request: &mut HttpRequest) -> Result<(), ParseError> {
+        self.check_body_length(request)?;
+        let length = Self::body_length(request)?;
+        let limit = self.body_limit(request);
+
+        if !Self::spools(request) {
+            let mut body = Vec::new();
+            self.copy_body(length, limit, &mut body, &mut request.trailers)?;
+            request.body = body;
+            return Ok(());
+        }
+
+        // Created first so the file is removed again if reading fails
+        let (mut spooled, file) = SpooledBody::create().map_err(ParseError::Spool)?;
+        let mut writer = BufWriter::new(file);
+        let size = self.copy_body(length, limit, &mut writer, &mut request.trailers)?;
+        writer.flush().map_err(ParseError::Spool)?;
+
+        spooled.length = size as u64;
+        request.spooled = Some(Arc::new(spooled));
+        Ok(())
+    }
+
+    // Uploads can be far larger than what is fine to hold in memory
+    fn spools(request: &HttpRequest) -> bool {
+        request
+            .header("Content-Type")
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|media| media.trim().eq_ignore_ascii_case("multipart/form-data"))
+    }
+
+    fn body_limit(&self, request: &HttpRequest) -> usize {
+        match Self::spools(request) {
+            true => self.limits.max_spooled_size,
+            false => self.limits.max_body_size,
+        }
+    }
+
+    fn body_length(request: &HttpRequest) -> Result<BodyLength, ParseError> {
+        // Repeated fields are read as one comma separated list
+        let joined = |name| match request.headers.get_all(name) {
+            values if values.is_empty() => None,
+            values => Some(values.join(", ")),
+        };
+        let transfer_encoding = joined("Transfer-Encoding");
+        let content_length = joined("Content-Length");
+
+        if let Some(encoding) = transfer_encoding {
+            // A message with both is a request smuggling vector, refuse it
+            if content_length.is_some() {
+                return Err(ParseError::InvalidContentLength);
+            }
+
+            // Only a bare "chunked" coding is supported, anything layered on
+            // top of it (gzip, deflate...) would need decoding we don't do
+            if !encoding.trim().eq_ignore_ascii_case("chunked") {
+                return Err(ParseError::UnsupportedTransferEncoding);
+            }
+
+            return Ok(BodyLength::Chunked);
+        }
+
+        match content_length {
+            // Repeated lengths must all agree
+            Some(value) => {
+                let mut lengths = value.split(',').map(|length| length.trim());
+                let first = lengths.next().unwrap_or("");
+                if first.is_empty()
+                    || !first.bytes().all(|b| b.is_ascii_digit())
+                    || lengths.any(|length| length != first)
+                {
+                    return Err(ParseError::InvalidContentLength);
+                }
+
+                first
+                    .parse()
+                    .map(BodyLength::Fixed)
+                    .map_err(|_| ParseError::InvalidContentLength)
+            }
+            None => Ok(BodyLength::Fixed(0)),
+        }
+    }
+
+    // Writes the body to `sink` and returns its size. Fixed lengths were checked
+    // against `limit` already, chunked bodies are checked as they arrive.
+    fn copy_body<W: Write>(
+        &mut self,
+        length: BodyLength,
+        limit: usize,
+        sink: &mut W,
+        trailers: &mut HeaderMap,
+    ) -> Result<usize, ParseError> {
+        match length {
+            BodyLength::Fixed(length) => {
+                self.copy_exact(length, sink)?;
+                Ok(length)
+            }
+            BodyLength::Chunked => self.copy_chunked(limit, sink, trailers),
+        }
+    }
+
+    fn copy_chunked<W: Write>(
+        &mut self,
+        limit: usize,
+        sink: &mut W,
+        trailers: &mut HeaderMap,
+    ) -> Result<usize, ParseError> {
+        let mut size = 0;
+
+        loop {
+            let line = self
+                .read_line(self.limits.max_request_line)
+                .map_err(|e| match e {
+                    ParseError::RequestLineTooLong => ParseError::InvalidChunk,
+                    e => e,
+                })?
+                .ok_or(ParseError::UnexpectedEof)?;
+
+            // Chunk extensions after ';' are allowed but carry nothing we use
+            let line = std::str::from_utf8(&line).map_err(|_| ParseError::InvalidChunk)?;
+            let digits = line.split(';').next().unwrap_or("").trim();
+            // from_str_radix would also take a sign
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ParseError::InvalidChunk);
+            }
+            // Sizes too big for usize are over any limit anyway
+            let chunk =
+                usize::from_str_radix(digits, 16).map_err(|_| ParseError::PayloadTooLarge)?;
+
+            if chunk == 0 {
+                break;
+            }
+            if chunk > limit - size {
+                return Err(ParseError::PayloadTooLarge);
+            }
+
+            self.copy_exact(chunk, sink)?;
+            size += chunk;
+
+            if self.read_exact(2)? != b"\r\n" {
+                return Err(ParseError::InvalidChunk);
+            }
+        }
+
+        self.read_fields(trailers)?;
+        Ok(size)
+    }
+
+    // Reads header (or trailer) lines up to and including the terminating empty line
+    fn read_fields(&mut self, fields: &mut HeaderMap) -> Result<(), ParseError> {
+        let mut size = 0;
+
+        loop {
+            let remaining = self.limits.max_header_size.saturating_sub(size);
+            let line = match self.read_line(remaining) {
+                Ok(Some(line)) => line,
+                Ok(None) => return Err(ParseError::UnexpectedEof),
+                Err(ParseError::RequestLineTooLong) => return Err(ParseError::HeadersTooLarge),
+                Err(e) => return Err(e),
+            };
+
+            if line.is_empty() {
+                return Ok(());
+            }
+
+            size += line.len() + 2;
+            if fields.len() >= self.limits.max_headers {
+                return Err(ParseError::TooManyHeaders);
+            }
+
+            // Repeated fields are kept as separate values
+            let (key, value) = Self::parse_header(&line)?;
+            fields
+                .try_append(&key, &value)
+                .map_err(|_| ParseError::InvalidHeader)?;
+        }
+    }
+
+    fn parse_request_line(line: &[u8], request: &mut HttpRequest) -> Result<(), ParseError> {
+        let line = std::str::from_utf8(line).map_err(|_| ParseError::InvalidRequestLine)?;
+        let parts: Vec<&str> = line.split(' ').collect();
+
+        if parts.len() != 3 || parts.iter().any(|part| part.is_empty()) {
+            return Err(ParseError::InvalidRequestLine);
+        }
+
+        request.method = HttpMethod::new(parts[0]);
+        if let HttpMethod::UNINITIALIZED = request.method {
+            return Err(ParseError::InvalidMethod);
+        }
+
+        // HTTP/2 has its own framing, a request line claiming it isn't one
+        request.version = HttpVersion::new(parts[2]);
+        if !matches!(request.version, HttpVersion::HTTP10 | HttpVersion::HTTP11) {
+            return Err(ParseError::InvalidVersion);
+        }
+
+        let target = parts[1];
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (target, ""),
+        };
+        request.path = path.to_string();
+        request.query_pairs = urlencoded::parse(query);
+        request.query = urlencoded::first_values(&request.query_pairs);
+
+        Ok(())
+    }
+
+    fn parse_header(line: &[u8]) -> Result<(String, String), ParseError> {
+        let line = std::str::from_utf8(line).map_err(|_| ParseError::InvalidHeader)?;
+
+        // Only split on the first colon so values like "localhost:7878" survive
+        let (key, value) = line.split_once(':').ok_or(ParseError::InvalidHeader)?;
+
+        // Whitespace is not allowed in the name or before the colon, and
+        // obsolete line folding (continuation lines) is rejected outright
+        if key.is_empty() || key.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(ParseError::InvalidHeader);
+        }
+
+        Ok((key.to_string(), value.trim().to_string()))
+    }
+
+    // Reads a single CRLF (or bare LF) terminated line without the terminator.
+    // Returns Ok(None) on a clean end of stream with nothing buffered.
+    fn read_line(&mut self, limit: usize) -> Result<Option<Vec<u8>>, ParseError> {
+        let mut searched = 0;
+
+        loop {
+            if let Some(pos) = self.buffer[searched..].iter().position(|&b| b == b'\n') {
+                let end = searched + pos;
+                if end > limit {
+                    return Err(ParseError::RequestLineTooLong);
+                }
+
+                let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return Ok(Some(line));
+            }
+
+            searched = self.buffer.len();
+            if searched > limit {
+                return Err(ParseError::RequestLineTooLong);
+            }
+
+            if self.fill()? == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Err(ParseError::UnexpectedEof);
+            }
+        }
+    }
+
+    fn read_exact(&mut self, length: usize) -> Result<Vec<u8>, ParseError> {
+        while self.buffer.len() < length {
+            if self.fill()? == 0 {
+                return Err(ParseError::UnexpectedEof);
+            }
+        }
+
+        Ok(self.buffer.drain(..length).collect())
+    }
+
+    // Passes `length` bytes on to `sink` as they arrive, without collecting them first
+    fn copy_exact<W: Write>(&mut self, mut length: usize, sink: &mut W) -> Result<(), ParseError> {
+        while length > 0 {
+            if self.buffer.is_empty() && self.fill()? == 0 {
+                return Err(ParseError::UnexpectedEof);
+            }
+
+            let n = length.min(self.buffer.len());
+            sink.write_all(&self.buffer[..n])
+                .map_err(ParseError::Spool)?;
+            self.buffer.drain(..n);
+            length -= n;
+        }
+
+        Ok(())
+    }
+
+    fn fill(&mut self) -> Result<usize, ParseError> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+
+        loop {
+            match self.reader.read(&mut chunk) {
+                Ok(n) => {
+                    self.buffer.extend_from_slice(&chunk[..n]);
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ParseError::Io(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn parse(input: &str) -> Result<Option<HttpRequest>, ParseError> {
+        RequestParser::new(input.as_bytes()).next_request()
+    }
+
+    fn error(input: &str) -> String {
+        match parse(input) {
+            Ok(_) => panic!("expected {:?} to be rejected", input),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn with_limits(input: &str, limits: ParserLimits) -> Result<Option<HttpRequest>, ParseError> {
+        RequestParser::with_limits(input.as_bytes(), limits).next_request()
+    }
+
+    // Hands out at most `step` bytes per read, like a slow connection
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buffer.len()).min(self.data.len());
+            buffer[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn request_line_and_headers() {
+        let request = parse(
+            "\r\n\r\nGET /search?q=a%20b&q=c HTTP/1.1\r\n\
+             Host: localhost:7878\r\n\
+             Accept: text/html\r\n\
+             accept: application/json\r\n\
+             X-Empty:\r\n\
+             \r\n",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(request.method == HttpMethod::GET);
+        assert_eq!(request.path, "/search");
+        assert_eq!(request.query.get("q").map(String::as_str), Some("a b"));
+        assert_eq!(request.query_values("q"), ["a b", "c"]);
+        assert_eq!(request.header("host"), Some("localhost:7878"));
+        assert_eq!(
+            request.headers.get_all("Accept"),
+            ["text/html", "application/json"]
+        );
+        assert_eq!(request.header("X-Empty"), Some(""));
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn bare_line_feeds_end_lines_too() {
+        let request = parse("GET / HTTP/1.1\nHost: x\n\n").unwrap().unwrap();
+        assert_eq!(request.header("Host"), Some("x"));
+    }
+
+    #[test]
+    fn malformed_heads() {
+        let cases = [
+            ("GET /\r\n\r\n", "malformed request line"),
+            ("GET  / HTTP/1.1\r\n\r\n", "malformed request line"),
+            ("GET / HTTP/1.1 extra\r\n\r\n", "malformed request line"),
+            ("BREW / HTTP/1.1\r\n\r\n", "unknown request method"),
+            (
+                "GET / HTTP/1.1\r\nNo colon\r\n\r\n",
+                "malformed header line",
+            ),
+            (
+                "GET / HTTP/1.1\r\nHost : x\r\n\r\n",
+                "malformed header line",
+            ),
+            ("GET / HTTP/1.1\r\n: x\r\n\r\n", "malformed header line"),
+            // Obsolete line folding
+            (
+                "GET / HTTP/1.1\r\nX-A: 1\r\n  2\r\n\r\n",
+                "malformed header line",
+            ),
+            (
+                "GET / HTTP/1.1\r\nBad\x01Name: x\r\n\r\n",
+                "malformed header line",
+            ),
+        ];
+
+        for (input, message) in cases {
+            assert_eq!(error(input), message, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn end_of_input() {
+        assert!(parse("").unwrap().is_none());
+        assert!(parse("\r\n").is_ok());
+        assert_eq!(
+            error("GET / HTTP/1.1\r\nHost: x\r\n"),
+            "connection closed mid-request"
+        );
+        assert_eq!(error("GET / HTTP/1.1"), "connection closed mid-request");
+    }
+
+    #[test]
+    fn head_limits() {
+        let limits = || ParserLimits {
+            max_request_line: 32,
+            max_header_size: 64,
+            max_headers: 3,
+            ..ParserLimits::default()
+        };
+
+        let long_path = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(32));
+        let e = with_limits(&long_path, limits()).unwrap_err();
+        assert_eq!(e.status_code(), StatusCode::URI_TOO_LONG);
+
+        let big_header = format!("GET / HTTP/1.1\r\nX-Big: {}\r\n\r\n", "a".repeat(64));
+        let e = with_limits(&big_header, limits()).unwrap_err();
+        assert_eq!(e.to_string(), "request headers too large");
+
+        // The limit is on all header lines together
+        let many_small = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-A: 123456789012345\r\n".repeat(3)
+        );
+        assert_eq!(
+            with_limits(&many_small, limits()).unwrap_err().to_string(),
+            "request headers too large"
+        );
+
+        let too_many = format!("GET / HTTP/1.1\r\n{}\r\n", "A: 1\r\n".repeat(4));
+        let e = with_limits(&too_many, limits()).unwrap_err();
+        assert_eq!(e.status_code(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+        let at_limits = format!("GET / HTTP/1.1\r\n{}\r\n", "A: 1\r\n".repeat(3));
+        assert!(with_limits(&at_limits, limits()).is_ok());
+    }
+
+    #[test]
+    fn requests_split_across_reads() {
+        let input = "POST /a HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhello\
+                     GET /b HTTP/1.1\r\nHost: x\r\n\r\n";
+
+        for step in [1, 2, 3, 7, 64] {
+            let reader = Trickle {
+                data: input.as_bytes(),
+                step,
+            };
+            let mut parser = RequestParser::new(reader);
+
+            let first = parser.next_request().unwrap().unwrap();
+            assert_eq!(
+                (first.path.as_str(), &first.body[..]),
+                ("/a", &b"hello"[..])
+            );
+            let second = parser.next_request().unwrap().unwrap();
+            assert_eq!(second.path, "/b");
+            assert!(parser.next_request().unwrap().is_none());
+        }
+    }
+
+    fn post(headers: &str, body: &str) -> String {
+        format!("POST / HTTP/1.1\r\nHost: x\r\n{}\r\n{}", headers, body)
+    }
+
+    #[test]
+    fn content_length_bodies() {
+        let request = parse(&post("Content-Length: 5\r\n", "hello"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.body, b"hello");
+
+        // Repeated lengths are fine as long as they agree
+        let repeated = post("Content-Length: 5\r\nContent-Length: 5\r\n", "hello");
+        assert_eq!(parse(&repeated).unwrap().unwrap().body, b"hello");
+        let listed = post("Content-Length: 5, 5\r\n", "hello");
+        assert_eq!(parse(&listed).unwrap().unwrap().body, b"hello");
+
+        for length in [
+            "5, 6",
+            "5\r\nContent-Length: 6",
+            "",
+            "+5",
+            "-1",
+            "0x5",
+            "5 5",
+            "99999999999999999999999",
+        ] {
+            let input = post(&format!("Content-Length: {}\r\n", length), "hello");
+            assert_eq!(error(&input), "invalid Content-Length", "{:?}", length);
+        }
+
+        assert_eq!(
+            error(&post("Content-Length: 10\r\n", "hello")),
+            "connection closed mid-request"
+        );
+    }
+
+    #[test]
+    fn content_length_with_transfer_encoding_is_refused() {
+        let body = "5\r\nhello\r\n0\r\n\r\n";
+        for headers in [
+            "Content-Length: 5\r\nTransfer-Encoding: chunked\r\n",
+            "Transfer-Encoding: chunked\r\nContent-Length: 5\r\n",
+        ] {
+            let e = parse(&post(headers, body)).unwrap_err();
+            assert_eq!(e.to_string(), "invalid Content-Length");
+            assert_eq!(e.status_code(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn only_chunked_transfer_coding_is_understood() {
+        for coding in [
+            "gzip",
+            "gzip, chunked",
+            "chunked, gzip",
+            "chunked\r\nTransfer-Encoding: chunked",
+        ] {
+            let input = post(&format!("Transfer-Encoding: {}\r\n", coding), "0\r\n\r\n");
+            let e = parse(&input).unwrap_err();
+            assert_eq!(e.status_code(), StatusCode::NOT_IMPLEMENTED, "{:?}", coding);
+        }
+    }
+
+    #[test]
+    fn chunked_bodies() {
+        let body = "5\r\nhello\r\nA;name=value\r\n, world!\r\n\r\n1 \r\n.\r\n0\r\n\r\n";
+        let request = parse(&post("Transfer-Encoding: Chunked\r\n", body))
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.body, b"hello, world!\r\n.");
+        assert!(request.trailers.is_empty());
+
+        let body = "3\r\nabc\r\n0\r\nChecksum: 123\r\nX-Done: yes\r\n\r\n";
+        let request = parse(&post("Transfer-Encoding: chunked\r\n", body))
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.body, b"abc");
+        assert_eq!(request.trailers.get("checksum"), Some("123"));
+        assert_eq!(request.trailers.get("X-Done"), Some("yes"));
+        // Trailers don't end up among the headers
+        assert!(request.header("Checksum").is_none());
+    }
+
+    #[test]
+    fn malformed_chunks() {
+        let cases = [
+            "+5\r\nhello\r\n0\r\n\r\n",
+            "-5\r\nhello\r\n0\r\n\r\n",
+            "0x5\r\nhello\r\n0\r\n\r\n",
+            "\r\nhello\r\n0\r\n\r\n",
+            "g\r\n0\r\n\r\n",
+            // Chunk longer than announced
+            "3\r\nhello\r\n0\r\n\r\n",
+        ];
+        for body in cases {
+            let input = post("Transfer-Encoding: chunked\r\n", body);
+            assert_eq!(error(&input), "malformed chunked body", "{:?}", body);
+        }
+
+        let input = post("Transfer-Encoding: chunked\r\n", "5\r\nhello\r\n");
+        assert_eq!(error(&input), "connection closed mid-request");
+        let input = post("Transfer-Encoding: chunked\r\n", "0\r\nBroken\r\n\r\n");
+        assert_eq!(error(&input), "malformed header line");
+    }
+
+    #[test]
+    fn body_limits() {
+        let limits = || ParserLimits {
+            max_body_size: 8,
+            ..ParserLimits::default()
+        };
+
+        let fixed = post("Content-Length: 9\r\n", "123456789");
+        assert_eq!(
+            with_limits(&fixed, limits()).unwrap_err().to_string(),
+            "request body too large"
+        );
+        let fixed = post("Content-Length: 8\r\n", "12345678");
+        assert!(with_limits(&fixed, limits()).is_ok());
+
+        // Chunked bodies are counted across chunks
+        let chunked = post(
+            "Transfer-Encoding: chunked\r\n",
+            "4\r\n1234\r\n5\r\n56789\r\n0\r\n\r\n",
+        );
+        let e = with_limits(&chunked, limits()).unwrap_err();
+        assert_eq!(e.status_code(), StatusCode::CONTENT_TOO_LARGE);
+        let chunked = post(
+            "Transfer-Encoding: chunked\r\n",
+            "4\r\n1234\r\n4\r\n5678\r\n0\r\n\r\n",
+        );
+        assert!(with_limits(&chunked, limits()).is_ok());
+
+        // Sizes that overflow usize are too large rather than malformed
+        for size in [
+            "ffffffffffffffffff",
+            "10000000000000000",
+            "0000000000000000000000009",
+        ] {
+            let chunked = post("Transfer-Encoding: chunked\r\n", &format!("{}\r\n", size));
+            let e = with_limits(&chunked, limits()).unwrap_err();
+            assert_eq!(e.to_string(), "request body too large", "{:?}", size);
+        }
+    }
+
+    #[test]
+    fn announced_lengths_are_checked_before_reading() {
+        let limits = ParserLimits {
+            max_body_size: 8,
+            ..ParserLimits::default()
+        };
+        let input = post("Content-Length: 100\r\n", "");
+        let mut parser = RequestParser::with_limits(input.as_bytes(), limits);
+        let request = parser.read_head().unwrap().unwrap();
+
+        let e = parser.check_body_length(&request).unwrap_err();
+        assert_eq!(e.to_string(), "request body too large");
+    }
+
+    #[test]
+    fn form_uploads_are_spooled_to_disk() {
+        let limits = || ParserLimits {
+            max_body_size: 4,
+            max_spooled_size: 16,
+            ..ParserLimits::default()
+        };
+        let headers = "Content-Type: multipart/form-data; boundary=x\r\nContent-Length: 10\r\n";
+
+        let request = with_limits(&post(headers, "0123456789"), limits())
+            .unwrap()
+            .unwrap();
+        assert!(request.body.is_empty());
+        let spooled = request.spooled.as_ref().unwrap();
+        assert_eq!(spooled.len(), 10);
+        assert_eq!(fs::read(spooled.path()).unwrap(), b"0123456789");
+
+        let headers = "Content-Type: multipart/form-data; boundary=x\r\nContent-Length: 17\r\n";
+        let e = with_limits(&post(headers, "01234567890123456"), limits()).unwrap_err();
+        assert_eq!(e.to_string(), "request body too large");
+    }
+
+    #[test]
+    fn methods_and_versions_are_case_sensitive() {
+        assert_eq!(error("get / HTTP/1.1\r\n\r\n"), "unknown request method");
+        assert_eq!(error("GET / http/1.1\r\n\r\n"), "unsupported HTTP version");
+    }
+
+    #[test]
+    fn only_http_1_is_spoken() {
+        for version in ["HTTP/1.0", "HTTP/1.1"] {
+            assert!(parse(&format!("GET / {}\r\n\r\n", version)).is_ok());
+        }
+        for version in ["HTTP/2.0", "HTTP/1.2", "HTTP/0.9", "HTTP/1"] {
+            let request = format!("GET / {}\r\n\r\n", version);
+            let e = parse(&request).unwrap_err();
+            assert_eq!(e.status_code(), StatusCode::HTTP_VERSION_NOT_SUPPORTED);
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use super::{Body, Conditional, ContentType, HttpMethod, HttpRequest, HttpResponse, StatusCode};
+use crate::crypto::random_bytes;
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+// More ranges than this in one request are ignored and the whole body is sent,
+// so clients can't make us assemble huge multipart responses out of tiny pieces
+const MAX_RANGES: usize = 32;
+
+// An inclusive range of byte offsets
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    // Number of bytes in the range, never zero
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+// What to do about the Range header of a request
+#[derive(PartialEq, Eq, Debug)]
+pub enum Ranges {
+    // No usable Range header, send the full body
+    Full,
+    Partial(Vec<ByteRange>),
+    // None of the ranges overlap the body, answer with a 416
+    Unsatisfiable,
+}
+
+impl Ranges {
+    // Evaluates the Range and If-Range headers of a GET request for a body of
+    // `length` bytes whose current validators are `conditional`
+    pub fn from_request(request: &HttpRequest, length: u64, conditional: &Conditional) -> Ranges {
+        if request.method != HttpMethod::GET {
+            return Ranges::Full;
+        }
+
+        match request.header("Range") {
+            Some(range) if conditional.if_range(request) => Ranges::parse(range, length),
+            _ => Ranges::Full,
+        }
+    }
+
+    // Parses a `bytes=0-99,200-,-50` header. Invalid headers and other units
+    // are ignored, overlapping and adjacent ranges are merged.
+    pub fn parse(value: &str, length: u64) -> Ranges {
+        let Some(specs) = value.trim().strip_prefix("bytes=") else {
+            return Ranges::Full;
+        };
+
+        let mut ranges = Vec::new();
+        for spec in specs
+            .split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+        {
+            let Some((first, last)) = spec.split_once('-') else {
+                return Ranges::Full;
+            };
+
+            let range = match (first.trim(), last.trim()) {
+                // The last n bytes
+                ("", suffix) => match suffix.parse::<u64>() {
+                    Ok(0) => None,
+                    Ok(n) if length > 0 => Some(ByteRange {
+                        start: length.saturating_sub(n),
+                        end: length - 1,
+                    }),
+                    Ok(_) => None,
+                    Err(_) => return Ranges::Full,
+                },
+                (first, last) => {
+                    let Ok(start) = first.parse::<u64>() else {
+                        return Ranges::Full;
+                    };
+                    let end = match last {
+                        "" => u64::MAX,
+                        last => match last.parse::<u64>() {
+                            Ok(end) if end >= start => end,
+                            _ => return Ranges::Full,
+                        },
+                    };
+
+                    (start < length).then(|| ByteRange {
+                        start,
+                        end: end.min(length - 1),
+                    })
+                }
+            };
+
+            ranges.extend(range);
+            if ranges.len() > MAX_RANGES {
+                return Ranges::Full;
+            }
+        }
+
+        if ranges.is_empty() {
+            return Ranges::Unsatisfiable;
+        }
+
+        // Sort and merge so no byte is sent twice
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end.saturating_add(1) => {
+                    last.end = last.end.max(range.end);
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        Ranges::Partial(merged)
+    }
+}
+
+// A 206 response with the requested ranges of `source`, which holds `length`
+// bytes of `content_type`. Several ranges are sent as multipart/byteranges.
+pub fn partial_response<R>(
+    mut source: R,
+    length: u64,
+    content_type: &ContentType,
+    ranges: &[ByteRange],
+) -> io::Result<HttpResponse>
+where
+    R: Read + Seek + Send + 'static,
+{
+    let mut response = HttpResponse::new(StatusCode::PARTIAL_CONTENT);
+    response.headers.insert("Accept-Ranges", "bytes");
+
+    if let [range] = ranges {
+        source.seek(SeekFrom::Start(range.start))?;
+
+        response
+            .headers
+            .insert("Content-Range", &content_range(range, length));
+        response.headers.set_content_type(content_type.clone());
+        response.headers.set_content_length(range.length());
+        response.body = Body::from_reader(source.take(range.length()), Some(range.length()));
+        return Ok(response);
+    }
+
+    let boundary: String = random_bytes(12)?
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    let mut parts = VecDeque::new();
+    for (i, range) in ranges.iter().enumerate() {
+        let head = format!(
+            "{}--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            if i == 0 { "" } else { "\r\n" },
+            boundary,
+            content_type,
+            content_range(range, length)
+        );
+        parts.push_back(Part::Bytes(head.into_bytes()));
+        parts.push_back(Part::Slice {
+            start: range.start,
+            remaining: range.length(),
+            positioned: false,
+        });
+    }
+    parts.push_back(Part::Bytes(
+        format!("\r\n--{}--\r\n", boundary).into_bytes(),
+    ));
+
+    let total = parts.iter().map(Part::len).sum();
+    response.headers.insert(
+        "Content-Type",
+        &format!("multipart/byteranges; boundary={}", boundary),
+    );
+    response.headers.set_content_length(total);
+    response.body = Body::from_reader(ByteRangesReader { source, parts }, Some(total));
+    Ok(response)
+}
+
+// A 416 telling the client how long the body actually is
+pub fn not_satisfiable(length: u64) -> HttpResponse {
+    let mut response = HttpResponse::new(StatusCode::RANGE_NOT_SATISFIABLE);
+    response
+        .headers
+        .insert("Content-Range", &format!("bytes */{}", length));
+    response
+}
+
+fn content_range(range: &ByteRange, length: u64) -> String {
+    format!("bytes {}-{}/{}", range.start, range.end, length)
+}
+
+enum Part {
+    Bytes(Vec<u8>),
+    Slice {
+        start: u64,
+        remaining: u64,
+        positioned: bool,
+    },
+}
+
+impl Part {
+    fn len(&self) -> u64 {
+        match self {
+            Part::Bytes(bytes) => bytes.len() as u64,
+            Part::Slice { remaining, .. } => *remaining,
+        }
+    }
+}
+
+// Streams the parts of a multipart/byteranges body, seeking the source to each range
+struct ByteRangesReader<R> {
+    source: R,
+    parts: VecDeque<Part>,
+}
+
+impl<R: Read + Seek> Read for ByteRangesReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while let Some(part) = self.parts.front_mut() {
+            match part {
+                Part::Bytes(bytes) if !bytes.is_empty() => {
+                    let n = bytes.len().min(buffer.len());
+                    buffer[..n].copy_from_slice(&bytes[..n]);
+                    bytes.drain(..n);
+                    return Ok(n);
+                }
+                Part::Slice {
+                    start,
+                    remaining,
+                    positioned,
+                } if *remaining > 0 => {
+                    if !*positioned {
+                        self.source.seek(SeekFrom::Start(*start))?;
+                        *positioned = true;
+                    }
+
+                    let wanted = buffer.len().min(*remaining as usize);
+                    let n = self.source.read(&mut buffer[..wanted])?;
+                    if n == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "file shrank while sending a range of it",
+                        ));
+                    }
+                    *remaining -= n as u64;
+                    return Ok(n);
+                }
+                _ => {
+                    self.parts.pop_front();
+                }
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(ranges: &[(u64, u64)]) -> Ranges {
+        Ranges::Partial(
+            ranges
+                .iter()
+                .map(|&(start, end)| ByteRange { start, end })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn range_forms() {
+        assert_eq!(Ranges::parse("bytes=0-99", 1000), partial(&[(0, 99)]));
+        assert_eq!(
+            Ranges::parse(" bytes= 10 - 19 ", 1000),
+            partial(&[(10, 19)])
+        );
+        // Open ended and past the end both stop at the last byte
+        assert_eq!(Ranges::parse("bytes=900-", 1000), partial(&[(900, 999)]));
+        assert_eq!(
+            Ranges::parse("bytes=900-5000", 1000),
+            partial(&[(900, 999)])
+        );
+        // Suffixes longer than the body are the whole body
+        assert_eq!(Ranges::parse("bytes=-50", 1000), partial(&[(950, 999)]));
+        assert_eq!(Ranges::parse("bytes=-5000", 1000), partial(&[(0, 999)]));
+    }
+
+    #[test]
+    fn ranges_are_sorted_and_merged() {
+        assert_eq!(
+            Ranges::parse("bytes=500-599,0-99,-100", 1000),
+            partial(&[(0, 99), (500, 599), (900, 999)])
+        );
+        // Overlapping and adjacent
+        assert_eq!(
+            Ranges::parse("bytes=0-99,50-149,150-199,300-399,250-310", 1000),
+            partial(&[(0, 199), (250, 399)])
+        );
+        assert_eq!(Ranges::parse("bytes=0-,-1,5-9", 1000), partial(&[(0, 999)]));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        assert_eq!(Ranges::parse("bytes=1000-", 1000), Ranges::Unsatisfiable);
+        assert_eq!(Ranges::parse("bytes=-0", 1000), Ranges::Unsatisfiable);
+        assert_eq!(Ranges::parse("bytes=0-0", 0), Ranges::Unsatisfiable);
+        assert_eq!(Ranges::parse("bytes=-10", 0), Ranges::Unsatisfiable);
+        // Satisfiable ones are served, the rest are dropped
+        assert_eq!(
+            Ranges::parse("bytes=2000-2999,0-9", 1000),
+            partial(&[(0, 9)])
+        );
+    }
+
+    #[test]
+    fn invalid_headers_mean_the_full_body() {
+        for value in [
+            "",
+            "bytes",
+            "items=0-9",
+            "bytes=5",
+            "bytes=9-5",
+            "bytes=a-9",
+            "bytes=0-9x",
+            "bytes=-x",
+            "bytes=--5",
+            "bytes=0-9,5",
+            "bytes=99999999999999999999-",
+        ] {
+            assert_eq!(Ranges::parse(value, 1000), Ranges::Full, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn too_many_ranges_mean_the_full_body() {
+        let specs = |n: u64| {
+            (0..n)
+                .map(|i| format!("{}-{}", i * 10, i * 10))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let at_limit = Ranges::parse(&format!("bytes={}", specs(MAX_RANGES as u64)), 1000);
+        assert!(matches!(at_limit, Ranges::Partial(ranges) if ranges.len() == MAX_RANGES));
+        let over_limit = format!("bytes={}", specs(MAX_RANGES as u64 + 1));
+        assert_eq!(Ranges::parse(&over_limit, 1000), Ranges::Full);
+    }
+
+    fn body(response: HttpResponse) -> String {
+        let mut body = String::new();
+        response
+            .body
+            .into_reader()
+            .read_to_string(&mut body)
+            .unwrap();
+        body
+    }
+
+    #[test]
+    fn single_range_response() {
+        let source = io::Cursor::new(b"0123456789".to_vec());
+        let range = ByteRange { start: 2, end: 5 };
+        let response = partial_response(source, 10, &ContentType::TEXT, &[range]).unwrap();
+
+        assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.header("Content-Range"), Some("bytes 2-5/10"));
+        assert_eq!(response.headers.content_length(), Some(4));
+        assert_eq!(body(response), "2345");
+    }
+
+    #[test]
+    fn multipart_byteranges_response() {
+        let source = io::Cursor::new(b"0123456789".to_vec());
+        let ranges = [
+            ByteRange { start: 0, end: 1 },
+            ByteRange { start: 7, end: 9 },
+        ];
+        let response = partial_response(source, 10, &ContentType::TEXT, &ranges).unwrap();
+
+        let content_type = response.header("Content-Type").unwrap().to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+        let length = response.headers.content_length();
+        let body = body(response);
+
+        assert_eq!(length, Some(body.len() as u64));
+        let expected = format!(
+            "--{b}\r\nContent-Type: {t}\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --{b}\r\nContent-Type: {t}\r\nContent-Range: bytes 7-9/10\r\n\r\n789\r\n\
+             --{b}--\r\n",
+            b = boundary,
+            t = ContentType::TEXT,
+        );
+        assert_eq!(body, expected);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::crypto::random_bytes;
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
+
+// A request body the parser wrote to a temporary file instead of memory, see
+// `ParserLimits::max_spooled_size`. The file is deleted when the last request
+// holding it is dropped.
+#[derive(Debug)]
+pub struct SpooledBody {
+    pub(crate) path: PathBuf,
+    pub(crate) length: u64,
+}
+
+impl SpooledBody {
+    // An empty body in a new temporary file, along with the file to write it to
+    pub(crate) fn create() -> io::Result<(SpooledBody, File)> {
+        let (file, path) = temp_file("request")?;
+        Ok((SpooledBody { path, length: 0 }, file))
+    }
+
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> io::Result<File> {
+        File::open(&self.path)
+    }
+}
+
+impl Drop for SpooledBody {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// A new file with a random name in the temporary directory, e.g. `upload-3f2a…`
+pub(crate) fn temp_file(prefix: &str) -> io::Result<(File, PathBuf)> {
+    let name: String = random_bytes(16)?
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let path = env::temp_dir().join(format!("{}-{}", prefix, name));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // The temporary directory is shared, other users mustn't read uploads
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let file = options.open(&path)?;
+    Ok((file, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn temp_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (spooled, _) = SpooledBody::create().unwrap();
+        let mode = fs::metadata(spooled.path()).unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn dropping_removes_the_file() {
+        let (spooled, _) = SpooledBody::create().unwrap();
+        let path = spooled.path().to_path_buf();
+        drop(spooled);
+
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+// Parses `key=value&other=value` pairs as sent in query strings and form bodies,
+// decoded and in order. Keys without `=` get an empty value, repeated keys are kept.
+pub fn parse(input: &str) -> Vec<(String, String)> {
+    input
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_query(key), decode_query(value))
+        })
+        .collect()
+}
+
+// One value per key, the first one where a key repeats
+pub fn first_values(pairs: &[(String, String)]) -> HashMap<String, String> {
+    pairs.iter().fold(HashMap::new(), |mut acc, (key, value)| {
+        acc.entry(key.to_string())
+            .or_insert_with(|| value.to_string());
+        acc
+    })
+}
+
+// Decodes a query or form component, where `+` stands for a space
+pub fn decode_query(input: &str) -> String {
+    decode(&input.replace('+', " "))
+}
+
+// Decodes `%XX` escapes. Malformed escapes are kept as they are and bytes that
+// don't form valid UTF-8 are replaced, so decoding never fails.
+pub fn decode(input: &str) -> String {
+    if !input.contains('%') {
+        return input.to_string();
+    }
+
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let (Some(high), Some(low)) = (
+                bytes.get(i + 1).and_then(|b| hex_value(*b)),
+                bytes.get(i + 2).and_then(|b| hex_value(*b)),
+            ) {
+                decoded.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn percent_escapes() {
+        assert_eq!(decode("plain"), "plain");
+        assert_eq!(decode("a%20b%2fc%2F"), "a b/c/");
+        assert_eq!(decode("caf%C3%A9"), "café");
+        // Paths keep `+` as it is
+        assert_eq!(decode("a+b"), "a+b");
+        // Malformed escapes stay, invalid UTF-8 is replaced
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+        assert_eq!(decode("%%41"), "%A");
+        assert_eq!(decode("%FF"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn query_components() {
+        assert_eq!(decode_query("a+b%20c"), "a b c");
+        // An escaped plus is a plus, not a space
+        assert_eq!(decode_query("1%2B1"), "1+1");
+        assert_eq!(decode_query("%2B+"), "+ ");
+    }
+
+    #[test]
+    fn pairs_in_order() {
+        assert_eq!(
+            parse("q=rust+web&tag=a&tag=b&flag&empty=&=x&&a%3Db=c%26d"),
+            owned(&[
+                ("q", "rust web"),
+                ("tag", "a"),
+                ("tag", "b"),
+                ("flag", ""),
+                ("empty", ""),
+                ("", "x"),
+                ("a=b", "c&d"),
+            ])
+        );
+        // Only the first `=` separates
+        assert_eq!(parse("k=v=w"), owned(&[("k", "v=w")]));
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn first_value_wins() {
+        let values = first_values(&parse("tag=a&tag=b&other=c"));
+
+        assert_eq!(values.len(), 2);
+        assert_eq!(values["tag"], "a");
+        assert_eq!(values["other"], "c");
+    }
+}
@@ -0,0 +1,207 @@
+use super::Value;
+use std::collections::{BTreeMap, HashMap};
+
+// Builds a typed value from parsed JSON, e.g. for the `Json<T>` extractor.
+// Errors describe what was wrong with the document.
+pub trait FromJson: Sized {
+    fn from_json(value: &Value) -> Result<Self, String>;
+}
+
+// Turns a typed value into JSON, e.g. for `Json<T>` responses
+pub trait ToJson {
+    fn to_json(&self) -> Value;
+}
+
+impl Value {
+    // A required field of an object, converted to `T`
+    pub fn field<T: FromJson>(&self, key: &str) -> Result<T, String> {
+        match self.get(key) {
+            Some(value) => T::from_json(value).map_err(|e| format!("`{}`: {}", key, e)),
+            None if matches!(self, Value::Object(_)) => Err(format!("missing field `{}`", key)),
+            None => Err("expected an object".to_string()),
+        }
+    }
+
+    // An optional field of an object, null counts as missing
+    pub fn optional_field<T: FromJson>(&self, key: &str) -> Result<Option<T>, String> {
+        match self.get(key) {
+            Some(Value::Null) | None => Ok(None),
+            Some(_) => self.field(key).map(Some),
+        }
+    }
+}
+
+impl FromJson for Value {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        Ok(value.clone())
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        value
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| "expected a string".to_string())
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        value
+            .as_bool()
+            .ok_or_else(|| "expected a boolean".to_string())
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        value
+            .as_f64()
+            .ok_or_else(|| "expected a number".to_string())
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        f64::from_json(value).map(|n| n as f32)
+    }
+}
+
+// Integers must be whole numbers that fit the type exactly
+macro_rules! from_json_integer {
+    ($($t:ty),+) => {
+        $(impl FromJson for $t {
+            fn from_json(value: &Value) -> Result<Self, String> {
+                match value {
+                    Value::Number(n) => n
+                        .as_i128()
+                        .and_then(|n| <$t>::try_from(n).ok())
+                        .ok_or_else(|| format!("expected a {}", stringify!($t))),
+                    _ => Err("expected a number".to_string()),
+                }
+            }
+        })+
+    };
+}
+
+from_json_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        value
+            .as_array()
+            .ok_or_else(|| "expected an array".to_string())?
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_json(item).map_err(|e| format!("[{}]: {}", i, e)))
+            .collect()
+    }
+}
+
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(key, value)| {
+                    T::from_json(value)
+                        .map(|value| (key.to_string(), value))
+                        .map_err(|e| format!("`{}`: {}", key, e))
+                })
+                .collect(),
+            _ => Err("expected an object".to_string()),
+        }
+    }
+}
+
+impl ToJson for Value {
+    fn to_json(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+macro_rules! to_json_number {
+    ($($t:ty),+) => {
+        $(impl ToJson for $t {
+            fn to_json(&self) -> Value {
+                Value::from(*self)
+            }
+        })+
+    };
+}
+
+to_json_number!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> Value {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Value {
+        self.as_ref().map_or(Value::Null, ToJson::to_json)
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> Value {
+        Value::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Value {
+        self.as_slice().to_json()
+    }
+}
+
+// Keys are sorted so the output doesn't change between runs
+impl<T: ToJson> ToJson for HashMap<String, T> {
+    fn to_json(&self) -> Value {
+        let mut fields: Vec<_> = self.iter().collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        Value::object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.as_str(), value.to_json())),
+        )
+    }
+}
+
+impl<T: ToJson> ToJson for BTreeMap<String, T> {
+    fn to_json(&self) -> Value {
+        Value::object(
+            self.iter()
+                .map(|(key, value)| (key.as_str(), value.to_json())),
+        )
+    }
+}
@@ -0,0 +1,9 @@
+pub use convert::{FromJson, ToJson};
+pub use number::Number;
+pub use parser::{parse, JsonError};
+pub use value::Value;
+
+pub mod convert;
+pub mod number;
+pub mod parser;
+pub mod value;
@@ -0,0 +1,70 @@
+use std::fmt::{self, Display, Formatter};
+
+// A JSON number. Integers are kept exactly, everything else (and integers
+// too long even for i128) is a float like in JavaScript.
+#[derive(Clone, Copy, Debug)]
+pub enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+impl Number {
+    // Parses a JSON number lexeme. Integers are kept exact so conversions to
+    // integer types can reject anything out of range instead of rounding it.
+    pub(crate) fn parse(lexeme: &str) -> Option<Number> {
+        let integer = !lexeme.contains(['.', 'e', 'E']);
+
+        match lexeme.parse::<i128>() {
+            Ok(n) if integer => Some(Number::Int(n)),
+            _ => lexeme.parse().ok().map(Number::Float),
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Int(n) => n as f64,
+            Number::Float(n) => n,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|n| i64::try_from(n).ok())
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128().and_then(|n| u64::try_from(n).ok())
+    }
+
+    // The exact integer value. Floats only count when they are whole and
+    // strictly inside the 64 bit range, 2^64 must not round down to u64::MAX.
+    pub(crate) fn as_i128(&self) -> Option<i128> {
+        match *self {
+            Number::Int(n) => Some(n),
+            Number::Float(n) if n.fract() == 0.0 && n >= -(2f64.powi(63)) && n < 2f64.powi(64) => {
+                Some(n as i128)
+            }
+            Number::Float(_) => None,
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a == b,
+            _ => self.as_f64() == other.as_f64(),
+        }
+    }
+}
+
+// JSON has no NaN or infinity, those are written as null like browsers do
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Number::Int(n) => write!(f, "{}", n),
+            Number::Float(n) if !n.is_finite() => write!(f, "null"),
+            Number::Float(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", n as i64),
+            Number::Float(n) => write!(f, "{}", n),
+        }
+    }
+}
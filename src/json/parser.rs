@@ -0,0 +1,340 @@
+use super::{Number, Value};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display},
+};
+
+// Deeply nested documents would otherwise overflow the stack
+const MAX_DEPTH: usize = 128;
+
+pub struct JsonError {
+    pub message: String,
+    // Byte offset into the input where parsing failed
+    pub position: usize,
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.position)
+    }
+}
+
+impl Debug for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JsonError({})", self)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+// Parses a complete JSON document, trailing content other than whitespace is an error
+pub fn parse(input: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        position: 0,
+        depth: 0,
+    };
+
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < parser.input.len() {
+        return Err(parser.error("Trailing characters"));
+    }
+
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, JsonError> {
+        if self.input[self.position..].starts_with(word.as_bytes()) {
+            self.position += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("Invalid literal"))
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        self.enter()?;
+        self.position += 1;
+
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            self.depth -= 1;
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(b',') => continue,
+                Some(b']') => break,
+                _ => return Err(self.error("Expected ',' or ']'")),
+            }
+        }
+
+        self.depth -= 1;
+        Ok(Value::Array(items))
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        self.enter()?;
+        self.position += 1;
+
+        let mut fields: Vec<(String, Value)> = Vec::new();
+        // Where each key sits in `fields`, a linear search would make large
+        // objects quadratic to parse
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            self.depth -= 1;
+            return Ok(Value::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("Expected a string key"));
+            }
+            let key = self.string()?;
+
+            self.skip_whitespace();
+            if self.next() != Some(b':') {
+                return Err(self.error("Expected ':'"));
+            }
+
+            // The last of duplicate keys wins, like in JavaScript
+            let value = self.value()?;
+            match positions.get(&key) {
+                Some(&index) => fields[index].1 = value,
+                None => {
+                    positions.insert(key.clone(), fields.len());
+                    fields.push((key, value));
+                }
+            }
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b'}') => break,
+                _ => return Err(self.error("Expected ',' or '}'")),
+            }
+        }
+
+        self.depth -= 1;
+        Ok(Value::Object(fields))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.position += 1;
+        let mut s = String::new();
+
+        loop {
+            // Copy runs of plain characters at once, the input is valid UTF-8 already
+            let start = self.position;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.position += 1;
+            }
+            s.push_str(std::str::from_utf8(&self.input[start..self.position]).unwrap_or(""));
+
+            match self.next() {
+                Some(b'"') => return Ok(s),
+                Some(b'\\') => self.escape(&mut s)?,
+                Some(_) => return Err(self.error("Control character in string")),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    fn escape(&mut self, s: &mut String) -> Result<(), JsonError> {
+        match self.next() {
+            Some(b'"') => s.push('"'),
+            Some(b'\\') => s.push('\\'),
+            Some(b'/') => s.push('/'),
+            Some(b'b') => s.push('\u{08}'),
+            Some(b'f') => s.push('\u{0c}'),
+            Some(b'n') => s.push('\n'),
+            Some(b'r') => s.push('\r'),
+            Some(b't') => s.push('\t'),
+            Some(b'u') => {
+                let unit = self.hex4()?;
+
+                // Characters outside the BMP come as a surrogate pair
+                let code = if (0xD800..0xDC00).contains(&unit) {
+                    if self.next() != Some(b'\\') || self.next() != Some(b'u') {
+                        return Err(self.error("Unpaired surrogate"));
+                    }
+                    let low = self.hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("Unpaired surrogate"));
+                    }
+                    0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    unit
+                };
+
+                match char::from_u32(code) {
+                    Some(c) => s.push(c),
+                    None => return Err(self.error("Invalid unicode escape")),
+                }
+            }
+            _ => return Err(self.error("Invalid escape")),
+        }
+
+        Ok(())
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .input
+            .get(self.position..self.position + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("Invalid unicode escape"))?;
+
+        self.position += 4;
+        Ok(digits)
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.position;
+
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.position += 1,
+            Some(b'1'..=b'9') => self.digits(),
+            _ => return Err(self.error("Invalid number")),
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if !self.peek().is_some_and(|b| b.is_ascii_digit()) {
+                return Err(self.error("Invalid number"));
+            }
+            self.digits();
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.position += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.position += 1;
+            }
+            if !self.peek().is_some_and(|b| b.is_ascii_digit()) {
+                return Err(self.error("Invalid number"));
+            }
+            self.digits();
+        }
+
+        std::str::from_utf8(&self.input[start..self.position])
+            .ok()
+            .and_then(Number::parse)
+            .map(Value::Number)
+            .ok_or_else(|| self.error("Invalid number"))
+    }
+
+    fn digits(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.position += 1;
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), JsonError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("Nesting too deep"));
+        }
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.position += 1;
+        Some(b)
+    }
+
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            message: message.to_string(),
+            position: self.position,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_keys_keep_the_first_position_and_the_last_value() {
+        let value = parse(r#"{"a": 1, "b": 2, "a": 3}"#).unwrap();
+
+        assert_eq!(value, Value::object([("a", 3.into()), ("b", 2.into())]));
+    }
+
+    // Looking each key up by scanning the earlier ones made this quadratic
+    #[test]
+    fn large_objects_parse_in_linear_time() {
+        let body = (0..100_000)
+            .map(|i| format!("\"{}\":{}", i, i))
+            .collect::<Vec<String>>()
+            .join(",");
+        let start = std::time::Instant::now();
+        let value = parse(&format!("{{{}}}", body)).unwrap();
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        let Value::Object(fields) = &value else {
+            panic!("expected an object");
+        };
+        assert_eq!(fields.len(), 100_000);
+        assert_eq!(value.get("99999").and_then(Value::as_i64), Some(99_999));
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for input in [
+            "", "{", r#"{"a"}"#, "[1,]", "01", "1.", r#""\x""#, "nul", "[] []",
+        ] {
+            assert!(parse(input).is_err(), "{:?}", input);
+        }
+    }
+}
@@ -1,11 +1,27 @@
+pub use compression::{Compression, Encoder};
+pub use extract::{
+    extract, Extract, Form, FromRequest, Header, Json, JsonConfig, Multipart, Path, Query, State,
+    UploadedFile,
+};
 pub use http::{
-    ContentType, HttpMethod, HttpRequest, HttpRequestHandler, HttpResponse, HttpVersion,
+    Body, ByteRange, Conditional, Connection, ContentType, Cookie, Cookies, ETag, Extensions,
+    HeaderMap, HttpError, HttpMethod, HttpRequest, HttpRequestHandler, HttpResponse, HttpVersion,
+    IntoResponse, KeepAlive, Key, ParseError, ParserLimits, Ranges, RequestParser, SameSite,
+    StatusCode, WriteOptions,
 };
-pub use router::{Route, Router};
-pub use templating::Template;
+pub use middleware::{Middleware, Next};
+pub use router::{handler, Constraint, Handler, Route, RouteMatch, Router, RouterError};
+pub use session::{FileStore, MemoryStore, Session, SessionStore, Sessions};
+pub use templating::{Template, TemplateError};
 pub use threading::{Job, ThreadPool, Worker};
 
+pub mod compression;
+pub mod crypto;
+pub mod extract;
 pub mod http;
+pub mod json;
+pub mod middleware;
 pub mod router;
+pub mod session;
 pub mod templating;
 pub mod threading;
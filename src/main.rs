@@ -1,73 +1,71 @@
 use rust_webserver::{
-    HttpRequest, HttpRequestHandler, HttpResponse, Route, Router, Template, ThreadPool,
-};
-use std::{
-    collections::HashMap,
-    io::prelude::*,
-    net::{TcpListener, TcpStream},
-    sync::Arc,
-    thread,
-    time::Duration,
+    handler, Compression, Connection, HttpError, HttpMethod, HttpRequest, HttpRequestHandler,
+    HttpResponse, Next, Route, Router, StatusCode, Template, ThreadPool,
 };
+use std::{collections::HashMap, net::TcpListener, sync::Arc, thread, time::Duration};
 
 static THREAD_COUNT: usize = 10;
 
 fn main() {
     let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
     let pool = ThreadPool::new(THREAD_COUNT);
-    let router = Arc::new(Router::new(create_routes()));
+    let mut router = Router::build(create_routes()).unwrap();
+    router
+        .fallback(|request| HttpRequestHandler::new(request).handle())
+        .wrap(log_requests)
+        .wrap(Compression::new())
+        .error_template(StatusCode::NOT_FOUND, "errors/404.html")
+        .unwrap()
+        .error_template(StatusCode::FORBIDDEN, "errors/403.html")
+        .unwrap();
+    let router = Arc::new(router);
 
     for stream in listener.incoming().take(THREAD_COUNT) {
-        let stream = stream.unwrap();
+        // A failed accept only affects that one client
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                println!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
         let rt = Arc::clone(&router);
 
         pool.execute(move || {
-            handle_connection(stream, rt);
+            Connection::new(stream).serve(|request| rt.handle(request));
         });
     }
 
     println!("Shutting down server.");
 }
 
-fn handle_connection(mut stream: TcpStream, router: Arc<Router>) {
-    let mut buffer = [0; 1024];
-    stream.read(&mut buffer).unwrap();
-
-    let request = HttpRequest::from(String::from_utf8_lossy(&buffer[..]).to_string());
-
-    if let Some(route) = router.get_handler(&request.path) {
-        let response = route.handle(request);
-
-        stream.write_all(&response.as_bytes()).unwrap();
-        stream.flush().unwrap();
-
-        return;
-    }
-
-    let response = HttpRequestHandler::new(request).handle();
+fn log_requests(request: HttpRequest, next: Next) -> HttpResponse {
+    let line = format!("{} {}", request.method, request.path);
+    let response = next.run(request);
 
-    stream.write_all(&response.as_bytes()).unwrap();
-    stream.flush().unwrap();
+    println!("{} -> {}", line, response.status);
+    response
 }
 
 fn create_routes() -> Vec<Route> {
     let mut routes = Vec::new();
 
     routes.push(Route::new(
+        HttpMethod::GET,
         "/sleep".to_string(),
-        Box::new(|_| {
+        handler(|_| {
             thread::sleep(Duration::from_secs(5));
-            HttpResponse::from("Hello, world!".to_string())
+            "Hello, world!"
         }),
     ));
 
     routes.push(Route::new(
+        HttpMethod::GET,
         "/hello".to_string(),
-        Box::new(|_| {
-            let mut template =
-                Template::build("hello".to_string(), "text.html".to_string()).unwrap();
+        handler(|_| -> Result<Template, HttpError> {
+            let mut template = Template::build("hello".to_string(), "text.html".to_string())?;
             template.render(HashMap::from([("name".to_string(), "World".to_string())]));
-            HttpResponse::from(template)
+            Ok(template)
         }),
     ));
 
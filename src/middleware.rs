@@ -0,0 +1,65 @@
+use super::{router::trie::split_path, HttpRequest, HttpResponse};
+
+// Code that runs around route handlers. A middleware gets the request and the
+// rest of the chain; it can change the request, answer on its own without
+// calling `next`, or change the response `next` returns.
+pub trait Middleware: Send + Sync {
+    fn handle(&self, request: HttpRequest, next: Next<'_>) -> HttpResponse;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(HttpRequest, Next<'_>) -> HttpResponse + Send + Sync,
+{
+    fn handle(&self, request: HttpRequest, next: Next<'_>) -> HttpResponse {
+        self(request, next)
+    }
+}
+
+// The remainder of the middleware chain, ending in the route handler
+pub struct Next<'a> {
+    middleware: &'a [&'a dyn Middleware],
+    endpoint: &'a dyn Fn(HttpRequest) -> HttpResponse,
+}
+
+impl<'a> Next<'a> {
+    pub fn new(
+        middleware: &'a [&'a dyn Middleware],
+        endpoint: &'a dyn Fn(HttpRequest) -> HttpResponse,
+    ) -> Next<'a> {
+        Next {
+            middleware,
+            endpoint,
+        }
+    }
+
+    pub fn run(self, request: HttpRequest) -> HttpResponse {
+        match self.middleware.split_first() {
+            Some((first, rest)) => first.handle(request, Next::new(rest, self.endpoint)),
+            None => (self.endpoint)(request),
+        }
+    }
+}
+
+// Middleware registered on a router, applied to every path under `prefix`
+pub(crate) struct Scoped {
+    pub prefix: String,
+    pub middleware: Box<dyn Middleware>,
+}
+
+impl Scoped {
+    // Compares whole segments of the path as the router splits it, so
+    // `/admin` covers `//admin/x` but not `/administrator`
+    pub fn applies_to<S: AsRef<str>>(&self, segments: &[S]) -> bool {
+        let prefix: Vec<&str> = split_path(&self.prefix)
+            .into_iter()
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        segments.len() >= prefix.len()
+            && prefix
+                .iter()
+                .zip(segments)
+                .all(|(expected, segment)| *expected == segment.as_ref())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::HttpMethod;
+use std::fmt::{self, Debug, Display};
+
+pub enum RouterError {
+    // Two routes for the same method and path shape, e.g. `GET /users/:id` and `GET /users/:name`
+    Conflict {
+        method: HttpMethod,
+        path: String,
+        existing: String,
+    },
+    InvalidPattern {
+        path: String,
+        reason: String,
+    },
+    // An error page template that doesn't exist under `templates/`
+    MissingTemplate {
+        path: String,
+    },
+}
+
+impl Display for RouterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouterError::Conflict {
+                method,
+                path,
+                existing,
+            } => write!(
+                f,
+                "route `{} {}` conflicts with already registered `{} {}`",
+                method, path, method, existing
+            ),
+            RouterError::InvalidPattern { path, reason } => {
+                write!(f, "invalid route pattern `{}`: {}", path, reason)
+            }
+            RouterError::MissingTemplate { path } => {
+                write!(f, "error page template `{}` not found", path)
+            }
+        }
+    }
+}
+
+impl Debug for RouterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RouterError({})", self)
+    }
+}
+
+impl std::error::Error for RouterError {}
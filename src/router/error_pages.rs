@@ -0,0 +1,116 @@
+use crate::{Body, ContentType, HttpMethod, HttpResponse, StatusCode, Template};
+use std::collections::HashMap;
+
+pub type ErrorHandler = Box<dyn Fn(&ErrorContext) -> HttpResponse + Send + Sync>;
+
+// What an error page gets to know about the failed request
+pub struct ErrorContext {
+    pub status: StatusCode,
+    pub method: HttpMethod,
+    pub path: String,
+    pub message: Option<String>,
+    pub request_id: u64,
+}
+
+impl ErrorContext {
+    // Template variables, HTML-escaped since paths and messages can come from the client
+    pub fn env(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("status".to_string(), self.status.as_u16().to_string()),
+            (
+                "reason".to_string(),
+                self.status.reason_phrase().to_string(),
+            ),
+            ("method".to_string(), self.method.to_string()),
+            ("path".to_string(), escape_html(&self.path)),
+            (
+                "message".to_string(),
+                escape_html(self.message.as_deref().unwrap_or("")),
+            ),
+            ("request_id".to_string(), self.request_id.to_string()),
+        ])
+    }
+}
+
+// Error handlers registered on a router, by status code with an optional catch-all.
+// Anything without a handler gets the built-in page.
+#[derive(Default)]
+pub struct ErrorPages {
+    handlers: HashMap<StatusCode, ErrorHandler>,
+    catch_all: Option<ErrorHandler>,
+}
+
+impl ErrorPages {
+    pub fn insert(&mut self, status: StatusCode, handler: ErrorHandler) {
+        self.handlers.insert(status, handler);
+    }
+
+    pub fn set_catch_all(&mut self, handler: ErrorHandler) {
+        self.catch_all = Some(handler);
+    }
+
+    pub fn render(&self, context: &ErrorContext) -> HttpResponse {
+        match self
+            .handlers
+            .get(&context.status)
+            .or(self.catch_all.as_ref())
+        {
+            Some(handler) => handler(context),
+            None => default_page(context),
+        }
+    }
+}
+
+// Renders a template from `templates/` with the context variables, falling
+// back to the built-in page if the template can't be loaded
+pub fn template_page(rel_path: &str, context: &ErrorContext) -> HttpResponse {
+    let Ok(mut template) = Template::build(rel_path.to_string(), rel_path.to_string()) else {
+        return default_page(context);
+    };
+
+    match &template.render(context.env()).rendered {
+        Some(rendered) => html_response(context.status, rendered.to_string()),
+        None => default_page(context),
+    }
+}
+
+pub fn default_page(context: &ErrorContext) -> HttpResponse {
+    let env = context.env();
+    let title = format!("{} {}", env["status"], env["reason"]);
+    let message = match &context.message {
+        Some(_) => format!("\n    <p>{}</p>", env["message"]),
+        None => String::new(),
+    };
+
+    html_response(
+        context.status,
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n  <head>\n    <meta charset=\"utf-8\" />\n    <title>{title}</title>\n  </head>\n  <body>\n    <h1>{title}</h1>{message}\n    <p><small>{} {} &middot; request {}</small></p>\n  </body>\n</html>\n",
+            env["method"], env["path"], env["request_id"]
+        ),
+    )
+}
+
+fn html_response(status: StatusCode, html: String) -> HttpResponse {
+    let mut response = HttpResponse::new(status);
+    response.headers.set_content_type(ContentType::HTML);
+    response.body = Body::from(html);
+    response
+}
+
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
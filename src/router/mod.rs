@@ -0,0 +1,402 @@
+pub use error::RouterError;
+pub use error_pages::{ErrorContext, ErrorHandler};
+pub use route::{handler, Handler, Route, RouteMatch};
+pub use trie::Constraint;
+
+pub mod error;
+pub mod error_pages;
+pub mod route;
+pub mod trie;
+
+use super::{
+    middleware::{Middleware, Next, Scoped},
+    Extensions, HttpMethod, HttpRequest, HttpResponse, IntoResponse, StatusCode, Template,
+};
+use crate::threading::panic;
+use error_pages::ErrorPages;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use trie::{decode_path, parse_pattern, Node};
+
+pub struct Router {
+    endpoints: Vec<Endpoint>,
+    root: Node,
+    fallback: Option<Handler>,
+    middleware: Vec<Scoped>,
+    errors: ErrorPages,
+    state: Extensions,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router {
+            endpoints: Vec::new(),
+            root: Node::default(),
+            fallback: None,
+            middleware: Vec::new(),
+            errors: ErrorPages::default(),
+            state: Extensions::new(),
+        }
+    }
+
+    pub fn build(routes: Vec<Route>) -> Result<Router, RouterError> {
+        let mut router = Router::new();
+
+        for route in routes {
+            router.insert(route)?;
+        }
+
+        Ok(router)
+    }
+
+    pub fn add_route(
+        &mut self,
+        method: HttpMethod,
+        path: String,
+        handler: Handler,
+    ) -> Result<&mut Self, RouterError> {
+        self.insert(Route::new(method, path, handler))?;
+        Ok(self)
+    }
+
+    pub fn get<F, R>(&mut self, path: &str, f: F) -> Result<&mut Self, RouterError>
+    where
+        F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.add_route(HttpMethod::GET, path.to_string(), handler(f))
+    }
+
+    pub fn post<F, R>(&mut self, path: &str, f: F) -> Result<&mut Self, RouterError>
+    where
+        F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.add_route(HttpMethod::POST, path.to_string(), handler(f))
+    }
+
+    pub fn put<F, R>(&mut self, path: &str, f: F) -> Result<&mut Self, RouterError>
+    where
+        F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.add_route(HttpMethod::PUT, path.to_string(), handler(f))
+    }
+
+    pub fn patch<F, R>(&mut self, path: &str, f: F) -> Result<&mut Self, RouterError>
+    where
+        F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.add_route(HttpMethod::PATCH, path.to_string(), handler(f))
+    }
+
+    pub fn delete<F, R>(&mut self, path: &str, f: F) -> Result<&mut Self, RouterError>
+    where
+        F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.add_route(HttpMethod::DELETE, path.to_string(), handler(f))
+    }
+
+    // Handles requests whose path matches no route, e.g. to serve static files
+    pub fn fallback<F, R>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.fallback = Some(handler(f));
+        self
+    }
+
+    // Shares a value with every handler of the router through the `State<T>` extractor
+    pub fn state<T: Send + Sync + 'static>(&mut self, value: T) -> &mut Self {
+        self.state.insert(value);
+        self
+    }
+
+    // Renders the page for error responses with `status` that have no body of their own
+    pub fn error_handler<F>(&mut self, status: StatusCode, handler: F) -> &mut Self
+    where
+        F: Fn(&ErrorContext) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.errors.insert(status, Box::new(handler));
+        self
+    }
+
+    // Renders `rel_path` from `templates/` for `status`, with `status`, `reason`,
+    // `method`, `path`, `message` and `request_id` available as variables
+    pub fn error_template(
+        &mut self,
+        status: StatusCode,
+        rel_path: &str,
+    ) -> Result<&mut Self, RouterError> {
+        if Template::build(rel_path.to_string(), rel_path.to_string()).is_err() {
+            return Err(RouterError::MissingTemplate {
+                path: rel_path.to_string(),
+            });
+        }
+
+        let rel_path = rel_path.to_string();
+        Ok(self.error_handler(status, move |context| {
+            error_pages::template_page(&rel_path, context)
+        }))
+    }
+
+    // Renders the page for every error status without a handler of its own,
+    // instead of the built-in page
+    pub fn default_error_handler<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(&ErrorContext) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.errors.set_catch_all(Box::new(handler));
+        self
+    }
+
+    // Runs a middleware around every request the router handles, in the order registered
+    pub fn wrap<M: Middleware + 'static>(&mut self, middleware: M) -> &mut Self {
+        self.wrap_prefix("/", middleware)
+    }
+
+    // Runs a middleware only around requests for paths under `prefix`
+    pub fn wrap_prefix<M: Middleware + 'static>(
+        &mut self,
+        prefix: &str,
+        middleware: M,
+    ) -> &mut Self {
+        self.middleware.push(Scoped {
+            prefix: prefix.to_string(),
+            middleware: Box::new(middleware),
+        });
+        self
+    }
+
+    // Moves every route, middleware and state of `router` under `prefix`, e.g. `/api/v1`.
+    // The mounted router's fallback and error handlers are not carried over.
+    pub fn mount(&mut self, prefix: &str, router: Router) -> Result<&mut Self, RouterError> {
+        self.state.extend(router.state);
+
+        for scoped in router.middleware {
+            self.middleware.push(Scoped {
+                prefix: join_paths(prefix, &scoped.prefix),
+                middleware: scoped.middleware,
+            });
+        }
+
+        for endpoint in router.endpoints {
+            for (mut route, _) in endpoint.routes {
+                route.path = join_paths(prefix, &route.path);
+                self.insert(route)?;
+            }
+        }
+
+        Ok(self)
+    }
+
+    // Combines routes defined elsewhere (e.g. in another module of the app) into this router
+    pub fn merge(&mut self, router: Router) -> Result<&mut Self, RouterError> {
+        self.mount("/", router)
+    }
+
+    // Registers a group of routes sharing a prefix and the middleware added inside `build`
+    pub fn group<F>(&mut self, prefix: &str, build: F) -> Result<&mut Self, RouterError>
+    where
+        F: FnOnce(&mut Router) -> Result<(), RouterError>,
+    {
+        let mut group = Router::new();
+        build(&mut group)?;
+
+        self.mount(prefix, group)
+    }
+
+    pub fn handle(&self, mut request: HttpRequest) -> HttpResponse {
+        request.extensions.extend(self.state.clone());
+
+        let method = request.method.clone();
+        let path = request.path.clone();
+        let id = request.id;
+
+        // Middleware scopes and routing must see the same decoded segments,
+        // or an escaped prefix like `/%61dmin` would slip past the middleware
+        let segments = decode_path(&request.path);
+        let chain: Vec<&dyn Middleware> = self
+            .middleware
+            .iter()
+            .filter(|scoped| scoped.applies_to(&segments))
+            .map(|scoped| scoped.middleware.as_ref())
+            .collect();
+
+        // A panicking handler answers with a 500 instead of taking down the
+        // connection. Its shared state may be half updated, so the connection
+        // is closed afterwards just like when the panic reaches it.
+        let dispatch = |request| self.dispatch(request, &segments);
+        let run = || Next::new(&chain, &dispatch).run(request);
+        let response = match catch_unwind(AssertUnwindSafe(run)) {
+            Ok(response) => response,
+            Err(payload) => {
+                println!(
+                    "Handler panicked on request {} ({} {}): {}",
+                    id,
+                    method,
+                    path,
+                    panic::message(&*payload)
+                );
+                let mut response = HttpResponse::internal_server_error();
+                response.headers.insert("Connection", "close");
+                response
+            }
+        };
+
+        // Handlers and middleware that answer with an error status and nothing
+        // else get the error page; bodies they set themselves are left alone
+        if !response.status.is_error() || !response.body.is_empty() {
+            return response;
+        }
+
+        let head = method == HttpMethod::HEAD;
+        let context = ErrorContext {
+            status: response.status,
+            method,
+            path,
+            message: response.message.clone(),
+            request_id: id,
+        };
+
+        // Keep headers like Allow or WWW-Authenticate from the original response
+        let mut page = self.errors.render(&context);
+        for (key, value) in response.headers {
+            if page.header(&key).is_none() && !key.eq_ignore_ascii_case("Content-Length") {
+                page.headers.append(&key, &value);
+            }
+        }
+
+        if head {
+            page = page.into_head();
+        }
+
+        page
+    }
+
+    // Dispatches a request to the route registered for its path and method.
+    // HEAD is answered by GET handlers and OPTIONS is answered automatically
+    // unless routes are registered for them, known paths with a method that
+    // has no route get a 405.
+    fn dispatch(&self, request: HttpRequest, segments: &[String]) -> HttpResponse {
+        let mut values = Vec::new();
+        let Some(index) = self.root.find(segments, &mut values) else {
+            return match &self.fallback {
+                Some(fallback) => fallback(request),
+                None => HttpResponse::not_found(),
+            };
+        };
+
+        let endpoint = &self.endpoints[index];
+
+        if let Some(found) = endpoint.find(&request.method, &values) {
+            return found.handle(request);
+        }
+
+        match request.method {
+            HttpMethod::HEAD => match endpoint.find(&HttpMethod::GET, &values) {
+                Some(found) => found.handle(request).into_head(),
+                None => HttpResponse::method_not_allowed(&endpoint.allowed_methods()),
+            },
+            HttpMethod::OPTIONS => HttpResponse::options(&endpoint.allowed_methods()),
+            _ => HttpResponse::method_not_allowed(&endpoint.allowed_methods()),
+        }
+    }
+
+    // Finds the route registered for a method and path along with the parameters it captured
+    pub fn find(&self, method: &HttpMethod, path: &str) -> Option<RouteMatch<'_>> {
+        let mut values = Vec::new();
+        let index = self.root.find(&decode_path(path), &mut values)?;
+
+        self.endpoints[index].find(method, &values)
+    }
+
+    fn insert(&mut self, route: Route) -> Result<(), RouterError> {
+        let segments = parse_pattern(&route.path)?;
+        let names = segments
+            .iter()
+            .filter_map(|segment| segment.name())
+            .map(|name| name.to_string())
+            .collect();
+
+        let index = self.root.insert(&segments, self.endpoints.len());
+        if index == self.endpoints.len() {
+            self.endpoints.push(Endpoint { routes: Vec::new() });
+        }
+
+        let endpoint = &mut self.endpoints[index];
+        if let Some((existing, _)) = endpoint
+            .routes
+            .iter()
+            .find(|(existing, _)| existing.method == route.method)
+        {
+            return Err(RouterError::Conflict {
+                method: route.method,
+                path: route.path,
+                existing: existing.path.to_string(),
+            });
+        }
+
+        endpoint.routes.push((route, names));
+        Ok(())
+    }
+}
+
+impl Default for Router {
+    fn default() -> Router {
+        Router::new()
+    }
+}
+
+// All routes sharing one path shape, at most one per method.
+// Each route keeps the parameter names from its own pattern.
+struct Endpoint {
+    routes: Vec<(Route, Vec<String>)>,
+}
+
+impl Endpoint {
+    fn find(&self, method: &HttpMethod, values: &[String]) -> Option<RouteMatch<'_>> {
+        self.routes
+            .iter()
+            .find(|(route, _)| route.method == *method)
+            .map(|(route, names)| RouteMatch {
+                route,
+                params: names.iter().cloned().zip(values.iter().cloned()).collect(),
+            })
+    }
+
+    fn has(&self, method: &HttpMethod) -> bool {
+        self.routes.iter().any(|(route, _)| route.method == *method)
+    }
+
+    fn allowed_methods(&self) -> Vec<HttpMethod> {
+        let mut allowed: Vec<HttpMethod> = self
+            .routes
+            .iter()
+            .map(|(route, _)| route.method.clone())
+            .collect();
+
+        if self.has(&HttpMethod::GET) && !self.has(&HttpMethod::HEAD) {
+            allowed.push(HttpMethod::HEAD);
+        }
+        if !self.has(&HttpMethod::OPTIONS) {
+            allowed.push(HttpMethod::OPTIONS);
+        }
+
+        allowed
+    }
+}
+
+fn join_paths(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    let path = path.trim_start_matches('/');
+
+    match (prefix.is_empty(), path.is_empty()) {
+        (true, true) => "/".to_string(),
+        (true, false) => format!("/{}", path),
+        (false, true) => prefix.to_string(),
+        (false, false) => format!("{}/{}", prefix, path),
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{HttpMethod, HttpRequest, HttpResponse, IntoResponse, JsonConfig};
+use std::collections::HashMap;
+
+pub type Handler = Box<dyn Fn(HttpRequest) -> HttpResponse + Send + Sync>;
+
+// Boxes a function returning anything that converts into a response, e.g.
+// `Result<HttpResponse, HttpError>`, as a route handler
+pub fn handler<F, R>(f: F) -> Handler
+where
+    F: Fn(HttpRequest) -> R + Send + Sync + 'static,
+    R: IntoResponse,
+{
+    Box::new(move |request| {
+        let config = JsonConfig::of(&request);
+        f(request).into_response_with(&config)
+    })
+}
+
+pub struct Route {
+    pub method: HttpMethod,
+    pub path: String,
+    pub handler: Handler,
+}
+
+impl Route {
+    pub fn new(method: HttpMethod, path: String, handler: Handler) -> Route {
+        Route {
+            method,
+            path: path.to_string(),
+            handler,
+        }
+    }
+
+    pub fn handle(&self, request: HttpRequest) -> HttpResponse {
+        (self.handler)(request)
+    }
+}
+
+pub struct RouteMatch<'a> {
+    pub route: &'a Route,
+    pub params: HashMap<String, String>,
+}
+
+impl RouteMatch<'_> {
+    pub fn handle(self, mut request: HttpRequest) -> HttpResponse {
+        request.params = self.params;
+        self.route.handle(request)
+    }
+}
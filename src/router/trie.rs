@@ -0,0 +1,344 @@
+use super::RouterError;
+use crate::http::urlencoded;
+use std::collections::HashMap;
+
+// Restricts which values a `:name<constraint>` parameter accepts
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    Int,
+    Alpha,
+    AlphaNumeric,
+    Slug,
+    Uuid,
+}
+
+impl Constraint {
+    pub fn new(s: &str) -> Option<Constraint> {
+        match s {
+            "int" => Some(Constraint::Int),
+            "alpha" => Some(Constraint::Alpha),
+            "alnum" => Some(Constraint::AlphaNumeric),
+            "slug" => Some(Constraint::Slug),
+            "uuid" => Some(Constraint::Uuid),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Constraint::Int => value.bytes().all(|b| b.is_ascii_digit()),
+            Constraint::Alpha => value.bytes().all(|b| b.is_ascii_alphabetic()),
+            Constraint::AlphaNumeric => value.bytes().all(|b| b.is_ascii_alphanumeric()),
+            Constraint::Slug => value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'),
+            Constraint::Uuid => {
+                value.len() == 36
+                    && value.char_indices().all(|(i, c)| match i {
+                        8 | 13 | 18 | 23 => c == '-',
+                        _ => c.is_ascii_hexdigit(),
+                    })
+            }
+        }
+    }
+}
+
+pub(crate) enum Segment {
+    Static(String),
+    Param(String, Option<Constraint>),
+    CatchAll(String),
+}
+
+impl Segment {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Segment::Static(_) => None,
+            Segment::Param(name, _) | Segment::CatchAll(name) => Some(name),
+        }
+    }
+}
+
+// Parses a route pattern like `/users/:id<int>/files/*path`
+pub(crate) fn parse_pattern(pattern: &str) -> Result<Vec<Segment>, RouterError> {
+    let parts = split_path(pattern);
+    let invalid = |reason: &str| RouterError::InvalidPattern {
+        path: pattern.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let mut segments = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        if let Some(name) = part.strip_prefix('*') {
+            if i != parts.len() - 1 {
+                return Err(invalid("catch-all segment must be last"));
+            }
+            if name.is_empty() {
+                return Err(invalid("unnamed catch-all segment"));
+            }
+
+            segments.push(Segment::CatchAll(name.to_string()));
+            continue;
+        }
+
+        let Some(param) = part.strip_prefix(':') else {
+            segments.push(Segment::Static(part.to_string()));
+            continue;
+        };
+
+        let (name, constraint) = match param.split_once('<') {
+            Some((name, rest)) => {
+                let constraint = rest
+                    .strip_suffix('>')
+                    .and_then(Constraint::new)
+                    .ok_or_else(|| invalid(&format!("unknown constraint `<{}`", rest)))?;
+                (name, Some(constraint))
+            }
+            None => (param, None),
+        };
+        if name.is_empty() {
+            return Err(invalid("unnamed parameter"));
+        }
+
+        segments.push(Segment::Param(name.to_string(), constraint));
+    }
+
+    let mut names: Vec<&str> = segments.iter().filter_map(|s| s.name()).collect();
+    names.sort();
+    if names.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(invalid("duplicate parameter name"));
+    }
+
+    Ok(segments)
+}
+
+// The segments of a request path with percent escapes decoded. Splitting
+// happens first, so an escaped `%2F` stays inside its segment.
+pub(crate) fn decode_path(path: &str) -> Vec<String> {
+    split_path(path)
+        .into_iter()
+        .map(urlencoded::decode)
+        .collect()
+}
+
+pub(crate) fn split_path(path: &str) -> Vec<&str> {
+    let path = path.trim_start_matches('/');
+
+    if path.is_empty() {
+        Vec::new()
+    } else {
+        path.split('/').collect()
+    }
+}
+
+// Routes are stored in a segment trie. Parameters are keyed by their constraint
+// only, so patterns that differ just in parameter names share a node.
+// Matching prefers static segments, then constrained parameters, then plain
+// parameters and finally catch-alls, backtracking when a more specific branch
+// doesn't lead to an endpoint.
+#[derive(Default)]
+pub(crate) struct Node {
+    endpoint: Option<usize>,
+    statics: HashMap<String, Node>,
+    params: Vec<(Option<Constraint>, Node)>,
+    catch_all: Option<usize>,
+}
+
+impl Node {
+    // Returns the endpoint registered for a pattern, assigning `index` if there is none yet
+    pub fn insert(&mut self, segments: &[Segment], index: usize) -> usize {
+        let Some((segment, rest)) = segments.split_first() else {
+            return *self.endpoint.get_or_insert(index);
+        };
+
+        match segment {
+            Segment::Static(part) => self
+                .statics
+                .entry(part.to_string())
+                .or_default()
+                .insert(rest, index),
+            Segment::Param(_, constraint) => {
+                let position = match self.params.iter().position(|(c, _)| c == constraint) {
+                    Some(position) => position,
+                    None => {
+                        // Constrained parameters are tried before unconstrained ones
+                        let position = match constraint {
+                            Some(_) => self
+                                .params
+                                .iter()
+                                .position(|(c, _)| c.is_none())
+                                .unwrap_or(self.params.len()),
+                            None => self.params.len(),
+                        };
+
+                        self.params.insert(position, (*constraint, Node::default()));
+                        position
+                    }
+                };
+
+                self.params[position].1.insert(rest, index)
+            }
+            Segment::CatchAll(_) => *self.catch_all.get_or_insert(index),
+        }
+    }
+
+    // Finds the endpoint for a path, collecting the captured values in pattern order
+    pub fn find(&self, segments: &[String], values: &mut Vec<String>) -> Option<usize> {
+        let Some((segment, rest)) = segments.split_first() else {
+            return self.endpoint.or_else(|| {
+                // A catch-all also matches an empty remainder
+                self.catch_all.inspect(|_| values.push(String::new()))
+            });
+        };
+
+        if let Some(found) = self
+            .statics
+            .get(segment.as_str())
+            .and_then(|node| node.find(rest, values))
+        {
+            return Some(found);
+        }
+
+        if !segment.is_empty() {
+            for (constraint, node) in &self.params {
+                if constraint.is_some_and(|c| !c.matches(segment)) {
+                    continue;
+                }
+
+                values.push(segment.clone());
+                if let Some(found) = node.find(rest, values) {
+                    return Some(found);
+                }
+                values.pop();
+            }
+        }
+
+        self.catch_all.inspect(|_| values.push(segments.join("/")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trie(patterns: &[&str]) -> Node {
+        let mut root = Node::default();
+        for (index, pattern) in patterns.iter().enumerate() {
+            root.insert(&parse_pattern(pattern).unwrap(), index);
+        }
+        root
+    }
+
+    // The index of the matching pattern and the captured values
+    fn find(root: &Node, path: &str) -> Option<(usize, Vec<String>)> {
+        let mut values = Vec::new();
+        let index = root.find(&decode_path(path), &mut values)?;
+        Some((index, values))
+    }
+
+    fn invalid(pattern: &str) -> String {
+        match parse_pattern(pattern) {
+            Err(RouterError::InvalidPattern { reason, .. }) => reason,
+            _ => panic!("expected {:?} to be rejected", pattern),
+        }
+    }
+
+    #[test]
+    fn more_specific_segments_win() {
+        let root = trie(&[
+            "/users/*rest",
+            "/users/:name",
+            "/users/:id<int>",
+            "/users/me",
+        ]);
+
+        assert_eq!(find(&root, "/users/me"), Some((3, vec![])));
+        assert_eq!(find(&root, "/users/42"), Some((2, vec!["42".into()])));
+        assert_eq!(find(&root, "/users/bob"), Some((1, vec!["bob".into()])));
+        assert_eq!(find(&root, "/users/bob/x"), Some((0, vec!["bob/x".into()])));
+        // A catch-all matches nothing at all too, parameters don't match empty segments
+        assert_eq!(find(&root, "/users"), Some((0, vec!["".into()])));
+        assert_eq!(find(&root, "/users/"), Some((0, vec!["".into()])));
+        assert_eq!(find(&root, "/"), None);
+    }
+
+    #[test]
+    fn dead_ends_backtrack() {
+        let root = trie(&[
+            "/a/static/x",
+            "/a/:p/y",
+            "/f/:id<int>/edit",
+            "/f/:name/view",
+            "/f/*rest",
+        ]);
+
+        assert_eq!(find(&root, "/a/static/x"), Some((0, vec![])));
+        assert_eq!(find(&root, "/a/static/y"), Some((1, vec!["static".into()])));
+        // Values captured on the way into a dead end are dropped again
+        assert_eq!(find(&root, "/f/42/view"), Some((3, vec!["42".into()])));
+        assert_eq!(find(&root, "/f/42/edit"), Some((2, vec!["42".into()])));
+        assert_eq!(find(&root, "/f/x/edit"), Some((4, vec!["x/edit".into()])));
+        assert_eq!(find(&root, "/a/static/z"), None);
+    }
+
+    #[test]
+    fn parameter_names_dont_split_nodes() {
+        let mut root = trie(&["/u/:id"]);
+
+        assert_eq!(root.insert(&parse_pattern("/u/:name").unwrap(), 1), 0);
+        assert_eq!(root.insert(&parse_pattern("/u/:id<int>").unwrap(), 1), 1);
+        assert_eq!(root.insert(&parse_pattern("/u/:n<int>").unwrap(), 2), 1);
+    }
+
+    #[test]
+    fn escaped_slashes_stay_in_their_segment() {
+        assert_eq!(decode_path("/files/a%2Fb/c%20d"), ["files", "a/b", "c d"]);
+
+        let root = trie(&["/files/:name", "/files/:dir/:name"]);
+        assert_eq!(find(&root, "/files/a%2Fb"), Some((0, vec!["a/b".into()])));
+        // Decoding happens after splitting, `%2F` can't reach another route
+        assert_eq!(
+            find(&root, "/files/%2e%2e%2Fsecret"),
+            Some((0, vec!["../secret".into()]))
+        );
+    }
+
+    #[test]
+    fn constraints() {
+        let uuid = "123e4567-e89b-12d3-a456-426614174000";
+        let root = trie(&[
+            "/i/:v<int>",
+            "/a/:v<alpha>",
+            "/n/:v<alnum>",
+            "/s/:v<slug>",
+            "/u/:v<uuid>",
+        ]);
+
+        assert!(find(&root, "/i/123").is_some());
+        assert!(find(&root, "/i/-1").is_none());
+        assert!(find(&root, "/a/abc").is_some());
+        assert!(find(&root, "/a/abc1").is_none());
+        assert!(find(&root, "/n/abc1").is_some());
+        assert!(find(&root, "/n/abc-1").is_none());
+        assert!(find(&root, "/s/abc-1_2").is_some());
+        assert!(find(&root, "/s/abc.1").is_none());
+        assert!(find(&root, &format!("/u/{}", uuid)).is_some());
+        assert!(find(&root, &format!("/u/{}", uuid.replace('-', "0"))).is_none());
+        assert!(find(&root, &format!("/u/{}", &uuid[..35])).is_none());
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert_eq!(
+            invalid("/files/*path/more"),
+            "catch-all segment must be last"
+        );
+        assert_eq!(invalid("/files/*"), "unnamed catch-all segment");
+        assert_eq!(invalid("/users/:"), "unnamed parameter");
+        assert_eq!(invalid("/users/:<int>"), "unnamed parameter");
+        assert_eq!(invalid("/users/:id<float>"), "unknown constraint `<float>`");
+        assert_eq!(invalid("/users/:id<int"), "unknown constraint `<int`");
+        assert_eq!(invalid("/:id/x/:id"), "duplicate parameter name");
+        assert_eq!(invalid("/:rest/*rest"), "duplicate parameter name");
+        assert!(parse_pattern("/").unwrap().is_empty());
+    }
+}
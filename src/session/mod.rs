@@ -0,0 +1,216 @@
+pub use store::{FileStore, MemoryStore, SessionData, SessionStore};
+
+pub mod store;
+
+use crate::{
+    crypto::{base64, random_bytes},
+    extract::FromRequest,
+    middleware::{Middleware, Next},
+    Cookie, HttpError, HttpRequest, HttpResponse, SameSite, StatusCode,
+};
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+// The session of the current request, available to handlers through the
+// `Session` extractor once the `Sessions` middleware is installed. Clones
+// share the same data.
+#[derive(Clone)]
+pub struct Session {
+    state: Arc<Mutex<SessionState>>,
+}
+
+struct SessionState {
+    id: Option<String>,
+    data: SessionData,
+    rotate: bool,
+    destroyed: bool,
+}
+
+impl Session {
+    fn new(id: Option<String>, data: SessionData) -> Session {
+        Session {
+            state: Arc::new(Mutex::new(SessionState {
+                id,
+                data,
+                rotate: false,
+                destroyed: false,
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SessionState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.state().data.get(key).cloned()
+    }
+
+    pub fn insert(&self, key: &str, value: &str) {
+        self.state().data.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn remove(&self, key: &str) -> Option<String> {
+        self.state().data.remove(key)
+    }
+
+    pub fn clear(&self) {
+        self.state().data.clear();
+    }
+
+    // Moves the data to a new id when the response is sent, e.g. after logging
+    // in, so an id planted by an attacker before login becomes worthless
+    pub fn rotate_id(&self) {
+        self.state().rotate = true;
+    }
+
+    // Drops the session from the store and the client, e.g. on logout
+    pub fn destroy(&self) {
+        let mut state = self.state();
+        state.data.clear();
+        state.destroyed = true;
+    }
+
+    // The id the client sent, None for a session that hasn't been saved yet
+    pub fn id(&self) -> Option<String> {
+        self.state().id.clone()
+    }
+}
+
+impl FromRequest for Session {
+    fn from_request(request: &HttpRequest) -> Result<Self, HttpError> {
+        match request.extensions.get::<Session>() {
+            Some(session) => Ok(session.clone()),
+            None => {
+                println!("Session extractor used without the Sessions middleware");
+                Err(HttpError::new(StatusCode::INTERNAL_SERVER_ERROR))
+            }
+        }
+    }
+}
+
+// Middleware loading the session named by the session cookie before the
+// handler runs and saving it afterwards. Empty sessions are never stored, so
+// clients only get a cookie once something is put in their session.
+pub struct Sessions {
+    store: Arc<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl Sessions {
+    pub fn new<S: SessionStore + 'static>(store: S) -> Sessions {
+        Sessions {
+            store: Arc::new(store),
+            cookie_name: "session".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            secure: false,
+        }
+    }
+
+    pub fn cookie_name(mut self, name: &str) -> Sessions {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    // How long a session lives without requests, every request that saves it starts over
+    pub fn ttl(mut self, ttl: Duration) -> Sessions {
+        self.ttl = ttl;
+        self
+    }
+
+    // Only send the cookie over HTTPS
+    pub fn secure(mut self, secure: bool) -> Sessions {
+        self.secure = secure;
+        self
+    }
+
+    fn load(&self, request: &HttpRequest) -> Session {
+        let Some(id) = request.cookie(&self.cookie_name) else {
+            return Session::new(None, SessionData::new());
+        };
+
+        match self.store.load(&id) {
+            Ok(Some(data)) => Session::new(Some(id), data),
+            Ok(None) => Session::new(None, SessionData::new()),
+            Err(e) => {
+                println!("Failed to load session: {}", e);
+                Session::new(None, SessionData::new())
+            }
+        }
+    }
+
+    fn save(&self, session: &Session, response: &mut HttpResponse) {
+        let state = session.state();
+
+        if state.destroyed || (state.data.is_empty() && state.id.is_some()) {
+            if let Some(id) = &state.id {
+                self.log_failure(self.store.destroy(id));
+                response.set_cookie(self.cookie(Cookie::removal(&self.cookie_name)));
+            }
+            return;
+        }
+        if state.data.is_empty() {
+            return;
+        }
+
+        let id = match (&state.id, state.rotate) {
+            (Some(id), false) => id.to_string(),
+            (old, _) => {
+                if let Some(old) = old {
+                    self.log_failure(self.store.destroy(old));
+                }
+
+                let id = match generate_id() {
+                    Ok(id) => id,
+                    Err(e) => {
+                        println!("Failed to generate a session id: {}", e);
+                        return;
+                    }
+                };
+                response.set_cookie(self.cookie(Cookie::new(&self.cookie_name, &id)));
+                id
+            }
+        };
+
+        // Saving unchanged sessions too keeps active sessions from expiring
+        self.log_failure(self.store.save(&id, &state.data, self.ttl));
+    }
+
+    // No Max-Age, the cookie lasts as long as the browser session and the
+    // store decides when the session itself expires
+    fn cookie(&self, cookie: Cookie) -> Cookie {
+        cookie
+            .path("/")
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax)
+    }
+
+    fn log_failure(&self, result: std::io::Result<()>) {
+        if let Err(e) = result {
+            println!("Failed to store session: {}", e);
+        }
+    }
+}
+
+impl Middleware for Sessions {
+    fn handle(&self, mut request: HttpRequest, next: Next<'_>) -> HttpResponse {
+        let session = self.load(&request);
+        request.extensions.insert(session.clone());
+
+        let mut response = next.run(request);
+        self.save(&session, &mut response);
+        response
+    }
+}
+
+// 32 random bytes, as URL-safe base64
+fn generate_id() -> std::io::Result<String> {
+    random_bytes(32).map(|bytes| base64::encode(&bytes))
+}
@@ -0,0 +1,344 @@
+use crate::json::{self, FromJson, Value};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub type SessionData = HashMap<String, String>;
+
+// How often saving a session also sweeps out the expired ones
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// Where sessions live between requests. Stores are shared by every worker.
+pub trait SessionStore: Send + Sync {
+    // The data saved under `id`, None if there is none or it expired
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>>;
+
+    // Saves `data` under `id`, replacing what was there, to expire after `ttl`
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()>;
+
+    fn destroy(&self, id: &str) -> io::Result<()>;
+}
+
+// Sessions in memory, lost on restart. Expired sessions are dropped when
+// they are looked up, and swept out by a save about once a minute.
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, (SessionData, Instant)>>,
+    last_sweep: Mutex<Option<Instant>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    // Removes every expired session
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        self.sessions().retain(|_, (_, expires)| *expires > now);
+    }
+
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, (SessionData, Instant)>> {
+        lock(&self.sessions)
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let mut sessions = self.sessions();
+
+        match sessions.get(id) {
+            Some((data, expires)) if *expires > Instant::now() => Ok(Some(data.clone())),
+            Some(_) => {
+                sessions.remove(id);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        if sweep_due(&self.last_sweep) {
+            self.sweep();
+        }
+
+        let expires = Instant::now() + ttl;
+        self.sessions()
+            .insert(id.to_string(), (data.clone(), expires));
+        Ok(())
+    }
+
+    fn destroy(&self, id: &str) -> io::Result<()> {
+        self.sessions().remove(id);
+        Ok(())
+    }
+}
+
+// Sessions as JSON files in a directory, one per session, so they survive
+// restarts. Expired files are removed when they are looked up, and swept out
+// by a save about once a minute.
+pub struct FileStore {
+    directory: PathBuf,
+    last_sweep: Mutex<Option<Instant>>,
+}
+
+impl FileStore {
+    pub fn new(directory: &str) -> io::Result<FileStore> {
+        fs::create_dir_all(directory)?;
+
+        Ok(FileStore {
+            directory: PathBuf::from(directory),
+            last_sweep: Mutex::new(None),
+        })
+    }
+
+    // Removes the files of expired sessions, and temporary files a crash left
+    // behind mid-save
+    pub fn sweep(&self) -> io::Result<()> {
+        let now = unix_time();
+
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let expired = match path.extension().and_then(|extension| extension.to_str()) {
+                Some("json") => fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|contents| json::parse(&contents).ok())
+                    .is_some_and(|file| file.field::<u64>("expires").unwrap_or(0) <= now),
+                Some("tmp") => fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > SWEEP_INTERVAL)),
+                _ => false,
+            };
+
+            if expired {
+                let _ = fs::remove_file(&path);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Ids come from a cookie, anything but the characters we generate could
+    // point outside the directory
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid session id",
+            ));
+        }
+
+        Ok(self.directory.join(format!("{}.json", id)))
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let path = match self.path(id) {
+            Ok(path) => path,
+            Err(_) => return Ok(None),
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        // A corrupt file is treated like a missing one and replaced on the next save
+        let Ok(file) = json::parse(&contents) else {
+            return Ok(None);
+        };
+        let expires: u64 = file.field("expires").unwrap_or(0);
+        if expires <= unix_time() {
+            let _ = fs::remove_file(&path);
+            return Ok(None);
+        }
+
+        Ok(file
+            .get("data")
+            .and_then(|data| SessionData::from_json(data).ok()))
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        // Sessions whose client never comes back are only found this way
+        // A failed sweep is retried next time, it mustn't lose this save
+        if sweep_due(&self.last_sweep) {
+            let _ = self.sweep();
+        }
+
+        let path = self.path(id)?;
+        let file = Value::object([
+            ("expires", (unix_time() + ttl.as_secs()).into()),
+            (
+                "data",
+                Value::object(
+                    data.iter()
+                        .map(|(key, value)| (key.as_str(), value.as_str().into())),
+                ),
+            ),
+        ]);
+
+        // Write then rename so a crash never leaves a half written session. Each
+        // write gets its own temporary file, concurrent saves of one id would
+        // otherwise write into the same one.
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let temporary = self.directory.join(format!(
+            "{}.{}-{}.tmp",
+            id,
+            process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written =
+            fs::write(&temporary, file.to_compact()).and_then(|_| fs::rename(&temporary, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        written
+    }
+
+    fn destroy(&self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Whether a sweep is due, claiming it for the caller if so
+fn sweep_due(last_sweep: &Mutex<Option<Instant>>) -> bool {
+    let mut last_sweep = lock(last_sweep);
+    if last_sweep.is_some_and(|last| last.elapsed() < SWEEP_INTERVAL) {
+        return false;
+    }
+
+    *last_sweep = Some(Instant::now());
+    true
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Arc, thread};
+
+    fn data(value: &str) -> SessionData {
+        HashMap::from([("user".to_string(), value.to_string())])
+    }
+
+    fn temp_store(name: &str) -> (FileStore, PathBuf) {
+        let directory = std::env::temp_dir().join(format!("sessions-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        (
+            FileStore::new(directory.to_str().unwrap()).unwrap(),
+            directory,
+        )
+    }
+
+    fn file_names(directory: &PathBuf) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn memory_store_expires_sessions() {
+        let store = MemoryStore::new();
+        store
+            .save("live", &data("a"), Duration::from_secs(60))
+            .unwrap();
+        store.save("dead", &data("b"), Duration::ZERO).unwrap();
+
+        assert_eq!(store.load("live").unwrap(), Some(data("a")));
+        assert_eq!(store.load("dead").unwrap(), None);
+
+        store.save("dead", &data("b"), Duration::ZERO).unwrap();
+        store.sweep();
+        assert_eq!(store.sessions().len(), 1);
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let (store, directory) = temp_store("round-trip");
+        store
+            .save("abc", &data("a"), Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(store.load("abc").unwrap(), Some(data("a")));
+        store.destroy("abc").unwrap();
+        assert_eq!(store.load("abc").unwrap(), None);
+        assert_eq!(store.load("../abc").unwrap(), None);
+        assert!(store
+            .save("../abc", &data("a"), Duration::from_secs(60))
+            .is_err());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn file_store_sweeps_sessions_nobody_loads() {
+        let (store, directory) = temp_store("sweep");
+        store
+            .save("live", &data("a"), Duration::from_secs(60))
+            .unwrap();
+        store.save("gone", &data("b"), Duration::ZERO).unwrap();
+        fs::write(directory.join("junk.txt"), "not a session").unwrap();
+
+        store.sweep().unwrap();
+        assert_eq!(file_names(&directory), ["junk.txt", "live.json"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn concurrent_saves_of_one_session() {
+        let (store, directory) = temp_store("concurrent");
+        let store = Arc::new(store);
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let store = Arc::clone(&store);
+                thread::spawn(move || {
+                    for _ in 0..50 {
+                        store
+                            .save("shared", &data(&i.to_string()), Duration::from_secs(60))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert!(store.load("shared").unwrap().is_some());
+        assert_eq!(file_names(&directory), ["shared.json"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,4 +1,10 @@
-use std::{collections::HashMap, env, fs::File, io::Read};
+use super::TemplateError;
+use std::{
+    collections::HashMap,
+    env,
+    fs::File,
+    io::{self, Read},
+};
 
 pub struct TemplateRenderer<'a> {
     pub template: &'a mut Template,
@@ -12,7 +18,7 @@ impl<'a> TemplateRenderer<'a> {
 
     pub fn render_text_vars(&mut self) {
         let mut buffer = [0; 1024];
-        if let Ok(_) = File::read(&mut self.template.file, &mut buffer) {
+        if File::read(&mut self.template.file, &mut buffer).is_ok() {
             let mut template = String::from_utf8(buffer.to_vec()).unwrap();
 
             for (key, value) in self.env_vars.iter() {
@@ -27,7 +33,7 @@ impl<'a> TemplateRenderer<'a> {
 
     pub fn render_js_fn(&mut self) {
         let mut buffer = [0; 1024];
-        if let Ok(_) = File::read(&mut self.template.file, &mut buffer) {
+        if File::read(&mut self.template.file, &mut buffer).is_ok() {
             let mut template = String::from_utf8(buffer.to_vec()).unwrap();
 
             // Generate a script tag to store the compiled template js functions
@@ -54,30 +60,28 @@ pub struct Template {
 }
 
 impl Template {
-    pub fn build(name: String, rel_path: String) -> Result<Self, String> {
-        let cwd = env::current_dir().unwrap();
+    pub fn build(name: String, rel_path: String) -> Result<Self, TemplateError> {
+        let cwd = env::current_dir()?;
         let path = cwd.join("templates").join(&rel_path);
 
-        if let Ok(file) = File::open(&path) {
-            Ok(Template {
+        match File::open(&path) {
+            Ok(file) => Ok(Template {
                 name,
                 path: path.as_path().display().to_string(),
                 file,
                 rendered: None,
                 env: HashMap::new(),
-            })
-        } else {
-            Err(format!("Template file not found: {}", rel_path))
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(TemplateError::NotFound(rel_path)),
+            Err(e) => Err(TemplateError::Io(e)),
         }
     }
 
     pub fn render(&mut self, env: HashMap<String, String>) -> &mut Self {
         self.env = env.clone();
 
-        let mut buffer = [0; 1024];
-        if let Ok(_) = File::read(&mut self.file, &mut buffer) {
-            let mut template = String::from_utf8(buffer.to_vec()).unwrap();
-
+        let mut template = String::new();
+        if self.file.read_to_string(&mut template).is_ok() {
             for (key, value) in env.iter() {
                 template = template.replace(&format!("{{{{ {} }}}}", key), value);
             }
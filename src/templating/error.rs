@@ -0,0 +1,33 @@
+use std::{
+    fmt::{self, Debug, Display},
+    io,
+};
+
+pub enum TemplateError {
+    // No file at the given path under `templates/`
+    NotFound(String),
+    Io(io::Error),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::NotFound(path) => write!(f, "Template file not found: {}", path),
+            TemplateError::Io(e) => write!(f, "Failed to read template: {}", e),
+        }
+    }
+}
+
+impl Debug for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TemplateError({})", self)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<io::Error> for TemplateError {
+    fn from(e: io::Error) -> TemplateError {
+        TemplateError::Io(e)
+    }
+}
@@ -1,3 +1,5 @@
 pub use engine::Template;
+pub use error::TemplateError;
 
 pub mod engine;
+pub mod error;
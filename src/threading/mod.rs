@@ -2,5 +2,6 @@ pub use thread_pool::Job;
 pub use thread_pool::ThreadPool;
 pub use worker::Worker;
 
+pub mod panic;
 pub mod thread_pool;
 pub mod worker;
@@ -1,10 +1,11 @@
-use super::Worker;
+use super::{panic, Worker};
 use std::sync::{mpsc, Arc, Mutex};
 
 pub type Job = Box<dyn FnOnce() + Send + 'static>;
 
 pub struct ThreadPool {
-    workers: Vec<Worker>,
+    workers: Mutex<Vec<Worker>>,
+    receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
     sender: Option<mpsc::Sender<Job>>,
 }
 
@@ -22,7 +23,8 @@ impl ThreadPool {
         }
 
         ThreadPool {
-            workers,
+            workers: Mutex::new(workers),
+            receiver,
             sender: Some(sender),
         }
     }
@@ -31,21 +33,50 @@ impl ThreadPool {
     where
         F: FnOnce() + Send + 'static,
     {
+        self.respawn_dead_workers();
+
         let job = Box::new(f);
 
         self.sender.as_ref().unwrap().send(job).unwrap();
     }
+
+    // Replaces workers whose thread died so the pool never shrinks
+    fn respawn_dead_workers(&self) {
+        let mut workers = self
+            .workers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for worker in workers.iter_mut().filter(|worker| worker.is_finished()) {
+            if let Some(thread) = worker.thread.take() {
+                if let Err(payload) = thread.join() {
+                    println!(
+                        "Worker {} died: {}; respawning.",
+                        worker.id,
+                        panic::message(&*payload)
+                    );
+                }
+            }
+
+            *worker = Worker::new(worker.id, Arc::clone(&self.receiver));
+        }
+    }
 }
 
 impl Drop for ThreadPool {
     fn drop(&mut self) {
         drop(self.sender.take());
 
-        for worker in &mut self.workers {
+        let workers = self
+            .workers
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for worker in workers {
             println!("Shutting down worker {}", worker.id);
 
             if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+                let _ = thread.join();
             }
         }
     }
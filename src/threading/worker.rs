@@ -1,6 +1,7 @@
 use {
-    super::Job,
+    super::{panic, Job},
     std::{
+        panic::{catch_unwind, AssertUnwindSafe},
         sync::{mpsc, Arc, Mutex},
         thread,
     },
@@ -14,13 +15,20 @@ pub struct Worker {
 impl Worker {
     pub fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
         let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv();
+            // Nothing panics while holding the lock, but a poisoned receiver is still usable
+            let message = receiver
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .recv();
 
             match message {
                 Ok(job) => {
                     println!("Worker {id} got a job; executing.");
 
-                    job();
+                    // A panicking job must not take the worker down with it
+                    if let Err(payload) = catch_unwind(AssertUnwindSafe(job)) {
+                        println!("Worker {id} job panicked: {}", panic::message(&*payload));
+                    }
                 }
                 Err(_) => {
                     println!("Worker {id} disconnected; shutting down.");
@@ -34,4 +42,11 @@ impl Worker {
             thread: Some(thread),
         }
     }
+
+    // Whether the thread has exited, which only happens on shutdown or if it died
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
 }